@@ -1,63 +1,243 @@
 use std::{env,fs,process};
+use std::collections::HashMap;
 
-fn run1(input: &str) -> i64 {
-    let lines = input.lines().map(|s| s.trim()).collect::<Vec<&str>>();
-    let mut registries = Vec::with_capacity(lines.len());
-    registries.push(1);
-    for line in lines {
-        let last = *registries.last().unwrap();
-        if line == "noop" {
-            registries.push(last);
-        } else if let Some(string) = line.strip_prefix("addx ") {
-            let num = string.parse::<i64>().unwrap();
-            registries.push(last);
-            registries.push(last + num);
-        } else {
-            panic!("Unexpected input");
+mod ocr;
+
+// Changes an instruction makes to the registers once its last cycle is over, given its arguments
+type Effect = fn(&mut [i64], &[i64]);
+
+#[derive(Clone,Copy)]
+struct Instruction {
+    opcode: &'static str,
+    cycles: usize,
+    arguments: usize,
+    effect: Effect,
+}
+
+// Gets to see the registers during every cycle, numbered from 1
+trait Observer {
+    fn tick(&mut self, cycle: usize, registers: &[i64]);
+}
+
+struct Cpu {
+    // X is register 0
+    registers: Vec<i64>,
+    table: HashMap<&'static str, Instruction>,
+    // Cycles completed so far
+    cycle: usize,
+}
+
+impl Cpu {
+    // A CPU knowing noop and addx, with X = 1 and the rest of registers at 0
+    fn new(registers: usize) -> Self {
+        assert!(registers > 0, "The CPU needs at least the X register");
+        let mut cpu = Self { registers: vec![0; registers], table: HashMap::new(), cycle: 0 };
+        cpu.registers[0] = 1;
+        cpu.define(Instruction { opcode: "noop", cycles: 1, arguments: 0, effect: |_, _| {} });
+        cpu.define(Instruction { opcode: "addx", cycles: 2, arguments: 1, effect: |r, a| r[0] += a[0] });
+        cpu
+    }
+
+    // Adds an instruction to the table, or replaces the one with the same opcode
+    fn define(&mut self, instruction: Instruction) {
+        self.table.insert(instruction.opcode, instruction);
+    }
+
+    // One instruction per line: an opcode followed by its integer arguments
+    fn parse(&self, input: &str) -> Result<Vec<(Instruction, Vec<i64>)>, String> {
+        let mut program = Vec::new();
+        for (k,line) in input.lines().enumerate().filter(|(_,l)| !l.trim().is_empty()) {
+            let mut words = line.split_whitespace();
+            let opcode = words.next().unwrap();
+            let instruction = *self.table.get(opcode).ok_or(format!("Line {}: unknown instruction {opcode}", k + 1))?;
+            let arguments = words.map(|w| w.parse::<i64>().map_err(|_| format!("Line {}: invalid argument {w}", k + 1)))
+                .collect::<Result<Vec<i64>, String>>()?;
+            if arguments.len() != instruction.arguments {
+                return Err(format!("Line {}: {opcode} takes {} arguments", k + 1, instruction.arguments));
+            }
+            program.push((instruction, arguments));
         }
+        Ok(program)
     }
-    let mut i = 19;
-    let mut sum = 0;
-    while i < registries.len() {
-        sum += registries[i] * ((i + 1) as i64);
-        i += 40;
-    }
-    sum
-}
-
-fn run2(input: &str) {
-    let lines = input.lines().map(|s| s.trim()).collect::<Vec<&str>>();
-    let mut registries = Vec::with_capacity(lines.len());
-    registries.push(1);
-    for line in lines {
-        let last = *registries.last().unwrap();
-        if line == "noop" {
-            registries.push(last);
-        } else if let Some(string) = line.strip_prefix("addx ") {
-            let num = string.parse::<i64>().unwrap();
-            registries.push(last);
-            registries.push(last + num);
-        } else {
-            panic!("Unexpected input");
+
+    fn run(&mut self, program: &[(Instruction, Vec<i64>)], observers: &mut [&mut dyn Observer]) {
+        for (instruction, arguments) in program {
+            for _ in 0..instruction.cycles {
+                self.cycle += 1;
+                for o in observers.iter_mut() {
+                    o.tick(self.cycle, &self.registers);
+                }
+            }
+            (instruction.effect)(&mut self.registers, arguments);
         }
     }
-    //println!("{registries:?}");
-    println!("{}", registries.len());
-    let mut row = String::from("");
-    for i in 0..241 {
-        let ii = (i % 40) as i64;
-        if i > 0 && ii == 0 {
-            println!("{row}");
-            row = String::from("");
+}
+
+// Sum of the cycle number times X during cycles 20, 60, 100...
+struct SignalStrength {
+    sum: i64,
+}
+
+impl Observer for SignalStrength {
+    fn tick(&mut self, cycle: usize, registers: &[i64]) {
+        if cycle % 40 == 20 {
+            self.sum += cycle as i64 * registers[0];
         }
-        if ii >= registries[i] - 1 && ii <= registries[i] + 1 {
-            row.push('#');
-        } else {
-            row.push('.');
+    }
+}
+
+const WIDTH: usize = 40;
+const HEIGHT: usize = 6;
+
+// Draws one pixel per cycle, lit when the 3 pixel wide sprite centered at X covers it
+struct Crt {
+    pixels: [[bool; WIDTH]; HEIGHT],
+}
+
+impl Crt {
+    fn new() -> Self {
+        Self { pixels: [[false; WIDTH]; HEIGHT] }
+    }
+
+    fn rows(&self) -> Vec<String> {
+        self.pixels.iter().map(|row| row.iter().map(|p| if *p { '#' } else { '.' }).collect()).collect()
+    }
+}
+
+impl Observer for Crt {
+    fn tick(&mut self, cycle: usize, registers: &[i64]) {
+        let (row, col) = ((cycle - 1) / WIDTH, (cycle - 1) % WIDTH);
+        if row < HEIGHT {
+            self.pixels[row][col] = (registers[0] - col as i64).abs() <= 1;
         }
     }
 }
 
+fn execute(input: &str, observers: &mut [&mut dyn Observer]) -> Cpu {
+    let mut cpu = Cpu::new(1);
+    let program = cpu.parse(input).unwrap_or_else(|e| panic!("{e}"));
+    cpu.run(&program, observers);
+    cpu
+}
+
+// X during every cycle
+struct Trace {
+    x: Vec<i64>,
+}
+
+impl Observer for Trace {
+    fn tick(&mut self, _cycle: usize, registers: &[i64]) {
+        self.x.push(registers[0]);
+    }
+}
+
+fn lit(cycle: usize, x: i64) -> bool {
+    (x - ((cycle - 1) % WIDTH) as i64).abs() <= 1
+}
+
+// The program, one instruction per line, each followed by the cycles it takes with X and the
+// pixel drawn during them
+fn disassemble(input: &str) -> Result<String, String> {
+    let mut cpu = Cpu::new(1);
+    let program = cpu.parse(input)?;
+    let mut trace = Trace { x: vec![] };
+    cpu.run(&program, &mut [&mut trace]);
+    let mut out = String::new();
+    let mut cycle = 1;
+    for (instruction, arguments) in &program {
+        let mut line = String::from(instruction.opcode);
+        for a in arguments {
+            line.push_str(&format!(" {a}"));
+        }
+        let cycles: Vec<String> = (cycle..cycle + instruction.cycles)
+            .map(|c| format!("{c}: X={} {}", trace.x[c - 1], if lit(c, trace.x[c - 1]) { '#' } else { '.' }))
+            .collect();
+        out.push_str(&format!("{line:<10} ; {}\n", cycles.join(", ")));
+        cycle += instruction.cycles;
+    }
+    Ok(out)
+}
+
+// Length of the shortest program reaching a cycle with some X, the cycle and X where its last
+// instruction started and what it added to X, if it was an addx
+type Step = (usize, (usize, i64), Option<i64>);
+
+// An addx/noop program drawing the image, given as rows of '#' for lit pixels and anything else
+// for dark ones. X only changes after an addx, so among the values that light exactly the right
+// pixels we look for the shortest program with dynamic programming over the cycles. X is 1 until
+// the third cycle, so images not starting with two lit pixels can't be drawn
+fn generate<S: AsRef<str>>(rows: &[S]) -> Result<String, String> {
+    if rows.len() != HEIGHT || rows.iter().any(|r| r.as_ref().chars().count() != WIDTH) {
+        return Err(format!("The image must have {HEIGHT} rows of {WIDTH} pixels"));
+    }
+    let target: Vec<Vec<bool>> = rows.iter().map(|r| r.as_ref().chars().map(|c| c == '#').collect()).collect();
+    let screen = WIDTH * HEIGHT;
+    // Some X in this range lights or leaves dark any pixel
+    let values: Vec<i64> = (-2..=WIDTH as i64 + 2).collect();
+    let index = |x: i64| (x + 2) as usize;
+    let fits = |cycle: usize, x: i64| cycle > screen || target[(cycle - 1) / WIDTH][(cycle - 1) % WIDTH] == lit(cycle, x);
+    // Indexed by the cycle where the next instruction starts and X during it
+    let mut best: Vec<Vec<Option<Step>>> = vec![vec![None; values.len()]; screen + 3];
+    best[1][index(1)] = Some((0, (0, 0), None));
+    // Last cycle some program draws right, along with all the ones before it
+    let mut drawn = 0;
+    for cycle in 1..=screen {
+        for &x in &values {
+            let Some((n,_,_)) = best[cycle][index(x)] else { continue };
+            if !fits(cycle, x) {
+                continue;
+            }
+            drawn = drawn.max(cycle);
+            let mut relax = |c: usize, y: i64, add: Option<i64>| {
+                if best[c][index(y)].is_none_or(|(m,_,_)| n + 1 < m) {
+                    best[c][index(y)] = Some((n + 1, (cycle, x), add));
+                }
+            };
+            relax(cycle + 1, x, None);
+            if fits(cycle + 1, x) {
+                drawn = drawn.max(cycle + 1);
+                for &y in &values {
+                    relax(cycle + 2, y, Some(y - x));
+                }
+            }
+        }
+    }
+    let end = (screen + 1..=screen + 2)
+        .flat_map(|c| values.iter().map(move |&x| (c, x)))
+        .filter(|&(c,x)| best[c][index(x)].is_some())
+        .min_by_key(|&(c,x)| best[c][index(x)].unwrap().0);
+    let Some((mut cycle, mut x)) = end else {
+        return Err(format!("No program draws row {} column {}", drawn / WIDTH + 1, drawn % WIDTH + 1));
+    };
+    let mut program = Vec::new();
+    while cycle > 1 {
+        let (_, previous, add) = best[cycle][index(x)].unwrap();
+        program.push(match add {
+            Some(n) => format!("addx {n}"),
+            None => "noop".to_string(),
+        });
+        (cycle, x) = previous;
+    }
+    program.reverse();
+    Ok(program.join("\n") + "\n")
+}
+
+fn run1(input: &str) -> i64 {
+    let mut signal = SignalStrength { sum: 0 };
+    execute(input, &mut [&mut signal]);
+    signal.sum
+}
+
+fn screen(input: &str) -> Vec<String> {
+    let mut crt = Crt::new();
+    execute(input, &mut [&mut crt]);
+    crt.rows()
+}
+
+fn run2(input: &str) -> String {
+    ocr::read(&screen(input)).unwrap_or_else(|e| panic!("{e}"))
+}
+
 fn main() {
     let mut args = env::args();
     let filepath;
@@ -72,9 +252,43 @@ fn main() {
 
     let input = fs::read_to_string(filepath).unwrap();
 
-    run2(&input);
-    //let res = run1(&input);
-    //println!("{res}");
+    match args.next().as_deref() {
+        Some("signal") => {
+            let res = run1(&input);
+            println!("{res}");
+        },
+        Some("disassemble") => match disassemble(&input) {
+            Ok(s) => print!("{s}"),
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(1);
+            },
+        },
+        // The file holds the image to draw, or the text with "write"
+        Some(mode @ ("generate" | "write")) => {
+            let image = if mode == "write" {
+                ocr::draw(input.trim(), WIDTH)
+            } else {
+                Ok(input.lines().map(String::from).collect())
+            };
+            match image.and_then(|rows| generate(&rows)) {
+                Ok(s) => print!("{s}"),
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                },
+            }
+        },
+        Some("screen") => {
+            for row in screen(&input) {
+                println!("{row}");
+            }
+        },
+        _ => {
+            let res = run2(&input);
+            println!("{res}");
+        },
+    }
 }
 
 #[test]
@@ -104,3 +318,47 @@ fn input1() {
     //let res = run2(&input);
     //assert_eq!(res,42);
 //}
+
+#[test]
+fn cpu() {
+    let mut cpu = Cpu::new(3);
+    // Moves X into another register, taking three cycles
+    cpu.define(Instruction { opcode: "movx", cycles: 3, arguments: 1, effect: |r, a| r[a[0] as usize] = r[0] });
+    let program = cpu.parse("addx 4\nmovx 2\nnoop\naddx -2\n").unwrap();
+    struct Trace(Vec<(usize, i64, i64)>);
+    impl Observer for Trace {
+        fn tick(&mut self, cycle: usize, registers: &[i64]) {
+            self.0.push((cycle, registers[0], registers[2]));
+        }
+    }
+    let mut trace = Trace(vec![]);
+    cpu.run(&program, &mut [&mut trace]);
+    assert_eq!(trace.0, vec![(1,1,0),(2,1,0),(3,5,0),(4,5,0),(5,5,0),(6,5,5),(7,5,5),(8,5,5)]);
+    assert_eq!(cpu.registers, vec![3,0,5]);
+    assert!(cpu.parse("jmp 3").is_err());
+    assert!(cpu.parse("addx").is_err());
+}
+
+#[test]
+fn generated_programs() {
+    for text in ["PHRZ", "EABCFGHI", "BJKLOPRS", "FUZ  Z"] {
+        let program = generate(&ocr::draw(text, WIDTH).unwrap()).unwrap();
+        assert_eq!(run2(&program), text);
+    }
+    let mut rows = vec![".".repeat(WIDTH); HEIGHT];
+    // X is 1 during the first cycle
+    assert_eq!(generate(&rows), Err("No program draws row 1 column 1".to_string()));
+    rows[0] = format!("##{}", ".".repeat(WIDTH - 2));
+    rows[2] = "#".repeat(WIDTH);
+    let program = generate(&rows).unwrap();
+    assert_eq!(screen(&program), rows);
+    assert!(generate(&rows[..5]).is_err());
+    assert_eq!(generate(&ocr::draw("HI", WIDTH).unwrap()), Err("No program draws row 1 column 2".to_string()));
+}
+
+#[test]
+fn disassembly() {
+    let expected = "noop       ; 1: X=1 #\naddx 3     ; 2: X=1 #, 3: X=1 #\naddx -5    ; 4: X=4 #, 5: X=4 #\n";
+    assert_eq!(disassemble("noop\naddx 3\naddx -5\n"), Ok(expected.to_string()));
+    assert!(disassemble("addx x").is_err());
+}
@@ -0,0 +1,106 @@
+// Recognition of the 4x6 font drawn by the puzzles, with '#' for lit pixels and anything else
+// for dark ones. Letters are 4 pixels wide and followed by an empty column
+
+pub const WIDTH: usize = 4;
+pub const HEIGHT: usize = 6;
+
+const FONT: [(char, [&str; HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+// Rows of the glyph for a letter, or None if the font doesn't have it
+pub fn glyph(c: char) -> Option<[&'static str; HEIGHT]> {
+    FONT.iter().find(|(l,_)| *l == c).map(|(_,g)| *g)
+}
+
+// The glyph in the cell starting at column x, normalized to '#' and '.'
+fn cell<S: AsRef<str>>(rows: &[S], x: usize) -> Vec<String> {
+    rows.iter().map(|row| {
+        let row: Vec<char> = row.as_ref().chars().collect();
+        (x..x + WIDTH).map(|i| if row.get(i) == Some(&'#') { '#' } else { '.' }).collect()
+    }).collect()
+}
+
+// Text drawn in the rows. Empty cells read as spaces, trailing ones are dropped. Every glyph not
+// in the font is reported in the error, with its position counted from 1 and its drawing
+pub fn read<S: AsRef<str>>(rows: &[S]) -> Result<String, String> {
+    if rows.len() != HEIGHT {
+        return Err(format!("Letters are {HEIGHT} rows high, got {} rows", rows.len()));
+    }
+    let width = rows.iter().map(|r| r.as_ref().chars().count()).max().unwrap_or(0);
+    let mut text = String::new();
+    let mut unknown = Vec::new();
+    for (k,x) in (0..width).step_by(WIDTH + 1).enumerate() {
+        let g = cell(rows, x);
+        if g.iter().all(|r| !r.contains('#')) {
+            text.push(' ');
+        } else if let Some((c,_)) = FONT.iter().find(|(_,f)| f.iter().zip(&g).all(|(a,b)| a == b)) {
+            text.push(*c);
+        } else {
+            unknown.push(format!("Unknown glyph at position {}:\n{}", k + 1, g.join("\n")));
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(unknown.join("\n"));
+    }
+    Ok(text.trim_end().to_string())
+}
+
+// Inverse of read: the text drawn in rows of the given width, with '#' and '.'. Spaces leave their
+// cell empty
+pub fn draw(text: &str, width: usize) -> Result<Vec<String>, String> {
+    let mut rows = vec![String::new(); HEIGHT];
+    for c in text.chars() {
+        let g = if c == ' ' { ["...."; HEIGHT] } else { glyph(c).ok_or(format!("No glyph for {c:?}"))? };
+        for (row,line) in rows.iter_mut().zip(g) {
+            if !row.is_empty() {
+                row.push('.');
+            }
+            row.push_str(line);
+        }
+    }
+    if rows[0].len() > width {
+        return Err(format!("{text:?} doesn't fit in {width} columns"));
+    }
+    for row in rows.iter_mut() {
+        row.extend((row.len()..width).map(|_| '.'));
+    }
+    Ok(rows)
+}
+
+#[test]
+fn letters() {
+    let rows = [
+        "###..#..#.###..####.",
+        "#..#.#..#.#..#....#.",
+        "#..#.####.#..#...#..",
+        "###..#..#.###...#...",
+        "#....#..#.#.#..#....",
+        "#....#..#.#..#.####.",
+    ];
+    assert_eq!(read(&rows), Ok("PHRZ".to_string()));
+    assert_eq!(read(&["#", "", "", "", "", ""]).unwrap_err(), "Unknown glyph at position 1:\n#...\n....\n....\n....\n....\n....");
+    let broken = ["####.#...", "#....#...", "###..###.", "#....#..#", "#....#..#", "####.###."];
+    assert_eq!(read(&broken).unwrap_err().lines().next(), Some("Unknown glyph at position 2:"));
+    assert!(read(&rows[..5]).is_err());
+    assert_eq!(draw("PHRZ", 20), Ok(rows.iter().map(|r| r.to_string()).collect()));
+    assert_eq!(read(&draw("HI  JO", 40).unwrap()), Ok("HI  JO".to_string()));
+    assert!(draw("HELLO", 20).is_err());
+    assert!(draw("hi", 20).is_err());
+}
@@ -1,76 +1,380 @@
 use std::{env,fs,process};
-use std::collections::{VecDeque,BinaryHeap};
+use std::collections::{VecDeque,BinaryHeap,HashMap};
+use std::cmp::Ordering;
 
-enum Atom {
-    Num(u64),
-    X,
+// Arithmetic the monkeys need on worry levels
+trait Worry: Clone {
+    // A constant of the same kind as self
+    fn constant(&self, n: u64) -> Self;
+    fn add(&self, other: &Self) -> Self;
+    fn neg(&self) -> Self;
+    fn mul(&self, other: &Self) -> Self;
+    // Integer division after an inspection, rounding towards zero
+    fn relieve(&self, divisor: u64) -> Self;
+    fn divisible(&self, divisor: u64) -> bool;
+
+    fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    fn pow(&self, mut exponent: u64) -> Self {
+        let mut base = self.clone();
+        let mut res = self.constant(1);
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                res = res.mul(&base);
+            }
+            base = base.mul(&base);
+            exponent /= 2;
+        }
+        res
+    }
 }
 
-impl Atom {
-    fn parse(string: &str) -> Self {
-        if string == "old" {
-            Atom::X
-        } else {
-            let num = string.parse().unwrap();
-            Atom::Num(num)
+// Absolute values as base 2^32 digits starting from the least significant one
+fn trim(digits: &mut Vec<u32>) {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+}
+
+fn cmp_digits(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_digits(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut digits = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let n = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        digits.push(n as u32);
+        carry = n >> 32;
+    }
+    digits.push(carry as u32);
+    trim(&mut digits);
+    digits
+}
+
+// Assumes a >= b
+fn sub_digits(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut digits = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i,d) in a.iter().enumerate() {
+        let n = *d as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        digits.push(n.rem_euclid(1 << 32) as u32);
+        borrow = if n < 0 { 1 } else { 0 };
+    }
+    trim(&mut digits);
+    digits
+}
+
+fn mul_digits(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut digits = vec![0u32; a.len() + b.len()];
+    for (i,x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j,y) in b.iter().enumerate() {
+            let n = *x as u64 * *y as u64 + digits[i + j] as u64 + carry;
+            digits[i + j] = n as u32;
+            carry = n >> 32;
         }
+        digits[i + b.len()] = carry as u32;
     }
+    trim(&mut digits);
+    digits
+}
 
-    fn replace(&self, rep: u64) -> u64 {
-        match self {
-            Atom::Num(n) => *n,
-            Atom::X => rep,
+// Arbitrary precision integers. Zero is never negative
+#[derive(Clone,Debug,PartialEq)]
+struct Big {
+    negative: bool,
+    digits: Vec<u32>,
+}
+
+impl Big {
+    fn new(n: u64) -> Self {
+        Big::from_digits(false, vec![n as u32, (n >> 32) as u32])
+    }
+
+    fn from_digits(negative: bool, mut digits: Vec<u32>) -> Self {
+        trim(&mut digits);
+        Big { negative: negative && !digits.is_empty(), digits }
+    }
+
+    // Quotient rounded towards zero and remainder of the absolute value, by a nonzero divisor
+    fn div_rem(&self, divisor: u64) -> (Self, u64) {
+        let mut quotient = vec![0; self.digits.len()];
+        let mut rem: u128 = 0;
+        for (i,d) in self.digits.iter().enumerate().rev() {
+            let n = (rem << 32) | *d as u128;
+            quotient[i] = (n / divisor as u128) as u32;
+            rem = n % divisor as u128;
+        }
+        (Big::from_digits(self.negative, quotient), rem as u64)
+    }
+
+    // Remainder between 0 and the modulus, even for negative numbers
+    fn rem_euclid(&self, modulus: u64) -> u64 {
+        let r = self.div_rem(modulus).1;
+        if self.negative && r != 0 { modulus - r } else { r }
+    }
+}
+
+impl std::fmt::Display for Big {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // Groups of 9 decimal digits, starting from the least significant
+        let mut groups = Vec::new();
+        let mut n = Big::from_digits(false, self.digits.clone());
+        while !n.digits.is_empty() {
+            let (q,r) = n.div_rem(1_000_000_000);
+            groups.push(r);
+            n = q;
+        }
+        if self.negative {
+            write!(f, "-")?;
         }
+        match groups.pop() {
+            None => write!(f, "0"),
+            Some(g) => {
+                write!(f, "{g}")?;
+                groups.iter().rev().try_for_each(|g| write!(f, "{g:09}"))
+            },
+        }
+    }
+}
+
+impl Worry for Big {
+    fn constant(&self, n: u64) -> Self {
+        Big::new(n)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        if self.negative == other.negative {
+            return Big::from_digits(self.negative, add_digits(&self.digits, &other.digits));
+        }
+        // The sign of the one with the greater absolute value wins
+        match cmp_digits(&self.digits, &other.digits) {
+            Ordering::Less => Big::from_digits(other.negative, sub_digits(&other.digits, &self.digits)),
+            _ => Big::from_digits(self.negative, sub_digits(&self.digits, &other.digits)),
+        }
+    }
+
+    fn neg(&self) -> Self {
+        Big::from_digits(!self.negative, self.digits.clone())
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        Big::from_digits(self.negative != other.negative, mul_digits(&self.digits, &other.digits))
+    }
+
+    fn relieve(&self, divisor: u64) -> Self {
+        self.div_rem(divisor).0
+    }
+
+    fn divisible(&self, divisor: u64) -> bool {
+        self.div_rem(divisor).1 == 0
+    }
+}
+
+// Worry level modulo a multiple of every test divisor, which keeps the result of every test and
+// is kept by sums, differences and products. Residues are below the modulus, so sums and products
+// of two of them fit in u128 and never overflow. Division doesn't commute with the reduction, so
+// residues can't be relieved
+#[derive(Clone,Debug,PartialEq)]
+struct Residue {
+    value: u64,
+    modulus: u64,
+}
+
+impl Worry for Residue {
+    fn constant(&self, n: u64) -> Self {
+        Residue { value: n % self.modulus, modulus: self.modulus }
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        let value = ((self.value as u128 + other.value as u128) % self.modulus as u128) as u64;
+        Residue { value, modulus: self.modulus }
     }
+
+    fn neg(&self) -> Self {
+        Residue { value: (self.modulus - self.value) % self.modulus, modulus: self.modulus }
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        let value = ((self.value as u128 * other.value as u128) % self.modulus as u128) as u64;
+        Residue { value, modulus: self.modulus }
+    }
+
+    fn relieve(&self, divisor: u64) -> Self {
+        assert_eq!(divisor, 1, "Residues can't be relieved");
+        self.clone()
+    }
+
+    fn divisible(&self, divisor: u64) -> bool {
+        self.value.is_multiple_of(divisor)
+    }
+}
+
+// Right hand side of the operation of a monkey, built only from ring operations so that it can
+// be evaluated on residues too. Exponents are natural number literals
+#[derive(Debug,PartialEq)]
+enum Expr {
+    Num(u64),
+    Old,
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Pow(Box<Expr>, u64),
 }
 
-enum Operation {
-    Sum(Atom, Atom),
-    Product(Atom, Atom),
+// Recursive descent over the tokens, with the usual precedence: sums and differences, then
+// products, then signs, then powers, which group from the right
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    next: usize,
 }
 
-impl Operation {
-    fn parse(string: &str) -> Self {
-        let words: Vec<&str> = string.split(' ').collect();
-        let atom1 = Atom::parse(words[0]);
-        let atom2 = Atom::parse(words[2]);
-        match words[1] {
-            "+" => Operation::Sum(atom1,atom2),
-            "*" => Operation::Product(atom1,atom2),
-            s => panic!("Unexpected input {s}"),
+impl<'a> Parser<'a> {
+    fn tokenize(string: &'a str) -> Result<Vec<&'a str>, String> {
+        let mut tokens = Vec::new();
+        let mut rest = string.trim_start();
+        while let Some(c) = rest.chars().next() {
+            let len = if c.is_ascii_alphanumeric() {
+                rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len())
+            } else if "+-*^()".contains(c) {
+                1
+            } else {
+                return Err(format!("Unexpected {c:?}"));
+            };
+            tokens.push(&rest[..len]);
+            rest = rest[len..].trim_start();
+        }
+        Ok(tokens)
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.next).copied()
+    }
+
+    fn take(&mut self) -> Option<&'a str> {
+        let t = self.peek();
+        self.next += 1;
+        t
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut e = self.product()?;
+        while let Some(op @ ("+" | "-")) = self.peek() {
+            self.next += 1;
+            let r = Box::new(self.product()?);
+            e = if op == "+" { Expr::Add(Box::new(e), r) } else { Expr::Sub(Box::new(e), r) };
+        }
+        Ok(e)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut e = self.sign()?;
+        while self.peek() == Some("*") {
+            self.next += 1;
+            e = Expr::Mul(Box::new(e), Box::new(self.sign()?));
         }
+        Ok(e)
     }
 
-    fn evaluate(&self, ev: u64, modulo: u32) -> u64 {
+    fn sign(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some("-") {
+            self.next += 1;
+            return Ok(Expr::Neg(Box::new(self.sign()?)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.peek() != Some("^") {
+            return Ok(base);
+        }
+        self.next += 1;
+        // a^b^c is a^(b^c)
+        let mut exponents = vec![self.exponent()?];
+        while self.peek() == Some("^") {
+            self.next += 1;
+            exponents.push(self.exponent()?);
+        }
+        let e = exponents.into_iter().rev()
+            .try_fold(1, |acc: u64, b| b.checked_pow(acc.try_into().map_err(|_| "Exponent too big")?).ok_or("Exponent too big"))?;
+        Ok(Expr::Pow(Box::new(base), e))
+    }
+
+    fn exponent(&mut self) -> Result<u64, String> {
+        match self.take() {
+            Some(t) => t.parse().map_err(|_| format!("Exponents must be natural numbers, not {t}")),
+            None => Err("Missing exponent".to_string()),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.take() {
+            Some("old") => Ok(Expr::Old),
+            Some("(") => {
+                let e = self.sum()?;
+                match self.take() {
+                    Some(")") => Ok(e),
+                    _ => Err("Expected )".to_string()),
+                }
+            },
+            Some(t) => t.parse().map(Expr::Num).map_err(|_| format!("Unexpected {t}")),
+            None => Err("Unexpected end of the operation".to_string()),
+        }
+    }
+}
+
+impl Expr {
+    fn parse(string: &str) -> Result<Self, String> {
+        let mut parser = Parser { tokens: Parser::tokenize(string)?, next: 0 };
+        let e = parser.sum()?;
+        match parser.peek() {
+            None => Ok(e),
+            Some(t) => Err(format!("Unexpected {t}")),
+        }
+    }
+
+    fn evaluate<W: Worry>(&self, old: &W) -> W {
         match self {
-            Operation::Sum(a1,a2) => (a1.replace(ev) + a2.replace(ev)) % (modulo as u64),
-            Operation::Product(a1,a2) => (a1.replace(ev) * a2.replace(ev)) % (modulo as u64),
+            Expr::Num(n) => old.constant(*n),
+            Expr::Old => old.clone(),
+            Expr::Neg(a) => a.evaluate(old).neg(),
+            Expr::Add(a,b) => a.evaluate(old).add(&b.evaluate(old)),
+            Expr::Sub(a,b) => a.evaluate(old).sub(&b.evaluate(old)),
+            Expr::Mul(a,b) => a.evaluate(old).mul(&b.evaluate(old)),
+            Expr::Pow(a,e) => a.evaluate(old).pow(*e),
         }
     }
 }
 
-struct Monkey {
+struct Monkey<W> {
     id: usize,
-    held_items: VecDeque<u64>,
-    operation: Operation,
-    test: u32,
+    held_items: VecDeque<W>,
+    operation: Expr,
+    test: u64,
     to_true: usize,
     to_false: usize,
     inspected: u64,
 }
 
-impl Monkey {
-    fn parse(input: &str) -> Self {
+impl<W: Worry> Monkey<W> {
+    // Starting worry levels become W with lift
+    fn parse(input: &str, lift: &dyn Fn(u64) -> W) -> Self {
         let mut lines = input.lines();
         let s1 = lines.next().unwrap().strip_prefix("Monkey ").unwrap().strip_suffix(':').unwrap();
         let id = s1.parse().unwrap();
         let s2 = lines.next().unwrap().trim().strip_prefix("Starting items: ").unwrap();
         let mut held_items = VecDeque::new();
         for s in s2.split(", ") {
-            held_items.push_back(s.parse().unwrap());
+            held_items.push_back(lift(s.parse().unwrap()));
         }
         let s3 = lines.next().unwrap().trim().strip_prefix("Operation: new = ").unwrap();
-        let operation = Operation::parse(s3);
+        let operation = Expr::parse(s3).unwrap_or_else(|e| panic!("Monkey {id}: {e}"));
         let s4 = lines.next().unwrap().trim().strip_prefix("Test: divisible by ").unwrap();
         let test = s4.parse().unwrap();
         let s5 = lines.next().unwrap().trim().strip_prefix("If true: throw to monkey ").unwrap();
@@ -80,85 +384,229 @@ impl Monkey {
         Self { id, held_items, operation, test, to_true, to_false, inspected: 0, }
     }
 
-    fn inspect(&mut self, divide: bool, modulo: u32) -> Option<(usize,u64)> {
-        if let Some(mut worry) = self.held_items.pop_front() {
-            worry = self.operation.evaluate(worry,modulo);
-            self.inspected += 1;
-            if divide {
-                worry /= 3;
-            }
-            if worry % (self.test as u64) == 0 {
-                Some((self.to_true, worry))
-            } else {
-                Some((self.to_false, worry))
-            }
+    // The same monkey holding the items converted with f
+    fn map<V>(self, f: impl Fn(&W) -> V) -> Monkey<V> {
+        Monkey {
+            id: self.id,
+            held_items: self.held_items.iter().map(f).collect(),
+            operation: self.operation,
+            test: self.test,
+            to_true: self.to_true,
+            to_false: self.to_false,
+            inspected: self.inspected,
+        }
+    }
+
+    // Where an item goes after this monkey inspects it, and its new worry level
+    fn throw(&self, worry: &W, relief: u64) -> (usize,W) {
+        let mut worry = self.operation.evaluate(worry);
+        if relief != 1 {
+            worry = worry.relieve(relief);
+        }
+        if worry.divisible(self.test) {
+            (self.to_true, worry)
         } else {
-            None
+            (self.to_false, worry)
         }
     }
+
+    fn inspect(&mut self, relief: u64) -> Option<(usize,W)> {
+        let worry = self.held_items.pop_front()?;
+        self.inspected += 1;
+        Some(self.throw(&worry, relief))
+    }
 }
 
-fn mcd(a: &u32, b: &u32) -> u32 {
-    if *b == 1 {
-        return *a;
+fn mcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        return a;
     }
-    mcd(b, &(a % b))
+    mcd(b, a % b)
 }
 
-fn mcm(a: &u32, b: &u32) -> u32 {
-    (a * b) / mcd(a,b)
+fn mcm(a: u64, b: u64) -> Option<u64> {
+    (a / mcd(a,b)).checked_mul(b)
 }
 
-fn run1(input: &str) -> u64 {
-    let mut monkeys = Vec::new();
-    let mut mult = 1;
-    // Read input
-    for string in input.split("\n\n") {
-        monkeys.push(Monkey::parse(string));
-        mult *= monkeys.last().unwrap().test;
-    }
-    // 20 rounds of monkeys inspecting elements
-    let number_rounds = 20;
-    for _ in 0..number_rounds {
+#[derive(Clone,Copy,PartialEq)]
+enum Mode {
+    // Whole worry levels, however big they get
+    Exact,
+    // Worry levels modulo the least common multiple of the test divisors
+    Modular,
+}
+
+// Number of items inspected by each monkey
+fn play<W: Worry>(mut monkeys: Vec<Monkey<W>>, rounds: usize, relief: u64) -> Vec<u64> {
+    for _ in 0..rounds {
         for i in 0..monkeys.len() {
-            while let Some((id,worry)) = monkeys[i].inspect(true, mult) {
+            while let Some((id,worry)) = monkeys[i].inspect(relief) {
                 monkeys[id].held_items.push_back(worry);
             }
         }
     }
-    // Sort monkeys by how many items they inspected
-    let mut priority = BinaryHeap::new();
-    for monkey in &monkeys {
-        priority.push(monkey.inspected);
+    monkeys.iter().map(|m| m.inspected).collect()
+}
+
+fn parse(input: &str) -> Vec<Monkey<Big>> {
+    input.split("\n\n").filter(|s| !s.trim().is_empty()).map(|s| Monkey::parse(s, &Big::new)).collect()
+}
+
+fn check_divisors(monkeys: &[Monkey<Big>]) -> Result<(), String> {
+    match monkeys.iter().find(|m| m.test == 0) {
+        Some(m) => Err(format!("Monkey {} tests divisibility by 0", m.id)),
+        None => Ok(()),
     }
-    // Multiply the two greater numbers
-    priority.pop().unwrap() * priority.pop().unwrap()
 }
 
-fn run2(input: &str) -> u64 {
-    let mut monkeys = Vec::new();
-    let mut mult = 1;
-    // Read input
-    for string in input.split("\n\n") {
-        monkeys.push(Monkey::parse(string));
-        mult *= monkeys.last().unwrap().test;
-    }
-    // 10000 rounds of monkeys inspecting elements
-    let number_rounds = 10000;
-    for _ in 0..number_rounds {
-        for i in 0..monkeys.len() {
-            while let Some((id,worry)) = monkeys[i].inspect(false, mult) {
-                monkeys[id].held_items.push_back(worry);
+// The monkeys holding their items modulo the least common multiple of the test divisors
+fn reduce(monkeys: Vec<Monkey<Big>>) -> Result<Vec<Monkey<Residue>>, String> {
+    check_divisors(&monkeys)?;
+    let mut modulus = 1;
+    for m in &monkeys {
+        modulus = mcm(modulus, m.test).ok_or("The test divisors' least common multiple overflows u64")?;
+    }
+    Ok(monkeys.into_iter().map(|m| m.map(|w| Residue { value: w.rem_euclid(modulus), modulus })).collect())
+}
+
+fn inspections(input: &str, rounds: usize, relief: u64, mode: Mode) -> Result<Vec<u64>, String> {
+    if relief == 0 {
+        return Err("The relief divisor can't be 0".to_string());
+    }
+    let monkeys = parse(input);
+    check_divisors(&monkeys)?;
+    match mode {
+        Mode::Exact => Ok(play(monkeys, rounds, relief)),
+        Mode::Modular => {
+            if relief != 1 {
+                return Err("Worry levels must be exact to be relieved".to_string());
+            }
+            Ok(play(reduce(monkeys)?, rounds, relief))
+        },
+    }
+}
+
+// Without relief items never affect each other, so each one follows its own route, which only
+// depends on the monkey holding it and its worry level at the start of a round. There are finitely
+// many of those modulo the test divisors, so every route ends up repeating
+struct Route {
+    // Monkey holding the item and its worry level at the start of every round, until the first
+    // one seen twice
+    states: Vec<(usize, Residue)>,
+    // Monkeys inspecting the item during each of those rounds
+    inspectors: Vec<Vec<usize>>,
+    // Round, counted from 0, from which states repeat
+    cycle_start: usize,
+}
+
+impl Route {
+    fn new(monkeys: &[Monkey<Residue>], mut monkey: usize, mut worry: Residue) -> Self {
+        let mut seen = HashMap::new();
+        let mut states = Vec::new();
+        let mut inspectors = Vec::new();
+        while !seen.contains_key(&(monkey, worry.value)) {
+            seen.insert((monkey, worry.value), states.len());
+            states.push((monkey, worry.clone()));
+            // Items thrown to a monkey yet to play get inspected again this round
+            let mut round = vec![monkey];
+            loop {
+                let (to, w) = monkeys[monkey].throw(&worry, 1);
+                let next = to > monkey;
+                (monkey, worry) = (to, w);
+                if !next {
+                    break;
+                }
+                round.push(monkey);
             }
+            inspectors.push(round);
         }
+        Route { states, inspectors, cycle_start: seen[&(monkey, worry.value)] }
+    }
+
+    fn cycle_len(&self) -> usize {
+        self.states.len() - self.cycle_start
     }
-    // Sort monkeys by how many items they inspected
-    let mut priority = BinaryHeap::new();
-    for monkey in &monkeys {
-        priority.push(monkey.inspected);
+
+    // Index into states and inspectors of a round counted from 0, however far
+    fn index(&self, round: u64) -> usize {
+        if round < self.states.len() as u64 {
+            round as usize
+        } else {
+            self.cycle_start + ((round - self.cycle_start as u64) % self.cycle_len() as u64) as usize
+        }
+    }
+
+    // Adds the inspections during the first rounds to counts
+    fn count(&self, rounds: u64, counts: &mut [u64]) {
+        let mut add = |range: std::ops::Range<usize>, times: u64| {
+            for round in &self.inspectors[range] {
+                for m in round {
+                    counts[*m] += times;
+                }
+            }
+        };
+        let len = self.states.len() as u64;
+        if rounds <= len {
+            add(0..rounds as usize, 1);
+            return;
+        }
+        add(0..self.cycle_start, 1);
+        let cycles = (rounds - self.cycle_start as u64) / self.cycle_len() as u64;
+        add(self.cycle_start..self.states.len(), cycles);
+        let rest = (rounds - self.cycle_start as u64) % self.cycle_len() as u64;
+        add(self.cycle_start..self.cycle_start + rest as usize, 1);
     }
-    // Multiply the two greater numbers
-    priority.pop().unwrap() * priority.pop().unwrap()
+}
+
+// Routes of every item, in the order they appear in the input
+fn routes(input: &str) -> Result<Vec<Route>, String> {
+    let monkeys = reduce(parse(input))?;
+    Ok(monkeys.iter().enumerate()
+        .flat_map(|(i,m)| m.held_items.iter().map(move |w| (i, w.clone())))
+        .map(|(i,w)| Route::new(&monkeys, i, w))
+        .collect())
+}
+
+// Number of items inspected by each monkey without relief, for any number of rounds
+fn extrapolate(input: &str, rounds: u64) -> Result<Vec<u64>, String> {
+    let routes = routes(input)?;
+    let mut counts = vec![0; parse(input).len()];
+    for r in &routes {
+        r.count(rounds, &mut counts);
+    }
+    Ok(counts)
+}
+
+// Monkeys inspecting an item, counted from 1, during the first rounds
+fn trace(input: &str, item: usize, rounds: u64) -> Result<String, String> {
+    let routes = routes(input)?;
+    let route = item.checked_sub(1).and_then(|i| routes.get(i)).ok_or(format!("There are {} items", routes.len()))?;
+    let mut out = String::new();
+    for round in 0..rounds {
+        let i = route.index(round);
+        let path: Vec<String> = route.inspectors[i].iter().map(|m| m.to_string()).collect();
+        out.push_str(&format!("Round {}: {}, worry {}\n", round + 1, path.join(" -> "), route.states[i].1.value));
+    }
+    out.push_str(&format!("Repeats every {} rounds from round {}\n", route.cycle_len(), route.cycle_start + 1));
+    Ok(out)
+}
+
+// Product of the two greatest numbers of inspected items
+fn business(inspected: Vec<u64>) -> u64 {
+    let mut priority: BinaryHeap<u64> = inspected.into_iter().collect();
+    priority.pop().unwrap_or(0) * priority.pop().unwrap_or(0)
+}
+
+fn monkey_business(input: &str, rounds: usize, relief: u64, mode: Mode) -> Result<u64, String> {
+    inspections(input, rounds, relief, mode).map(business)
+}
+
+fn run1(input: &str) -> u64 {
+    monkey_business(input, 20, 3, Mode::Exact).unwrap_or_else(|e| panic!("{e}"))
+}
+
+fn run2(input: &str) -> u64 {
+    monkey_business(input, 10000, 1, Mode::Modular).unwrap_or_else(|e| panic!("{e}"))
 }
 
 fn main() {
@@ -175,8 +623,58 @@ fn main() {
 
     let input = fs::read_to_string(filepath).unwrap();
 
-    let res = run2(&input);
-    println!("{res}");
+    match args.next().as_deref() {
+        Some("relieved") => println!("{}", run1(&input)),
+        // Rounds, relief divisor and optionally "exact"
+        Some("play") => {
+            let numbers: Vec<u64> = args.by_ref().take(2).filter_map(|s| s.parse().ok()).collect();
+            if numbers.len() != 2 {
+                eprintln!("How many rounds, and how relieved? Give me two numbers!");
+                process::exit(1);
+            }
+            let mode = if args.next().as_deref() == Some("exact") { Mode::Exact } else { Mode::Modular };
+            match monkey_business(&input, numbers[0] as usize, numbers[1], mode) {
+                Ok(res) => println!("{res}"),
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                },
+            }
+        },
+        // Any number of rounds without relief, like 1000000000000
+        Some("rounds") => {
+            let rounds = args.next().and_then(|s| s.parse().ok()).unwrap_or_else(|| {
+                eprintln!("How many rounds? Give me a number!");
+                process::exit(1);
+            });
+            match extrapolate(&input, rounds) {
+                Ok(counts) => println!("{}", business(counts)),
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                },
+            }
+        },
+        // Item, counted from 1, and rounds
+        Some("trace") => {
+            let numbers: Vec<u64> = args.by_ref().take(2).filter_map(|s| s.parse().ok()).collect();
+            if numbers.len() != 2 {
+                eprintln!("Which item, and for how many rounds? Give me two numbers!");
+                process::exit(1);
+            }
+            match trace(&input, numbers[0] as usize, numbers[1]) {
+                Ok(s) => print!("{s}"),
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                },
+            }
+        },
+        _ => {
+            let res = run2(&input);
+            println!("{res}");
+        },
+    }
 }
 
 #[test]
@@ -206,3 +704,99 @@ fn input2() {
     let res = run2(&input);
     assert_eq!(res,30893109657);
 }
+
+#[cfg(test)]
+const EXAMPLE: &str = "Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1
+";
+
+#[test]
+fn big_numbers() {
+    let a = Big::new(u64::MAX);
+    let b = a.mul(&a).add(&Big::new(7));
+    assert_eq!(b.to_string(), "340282366920938463426481119284349108232");
+    assert_eq!(b.div_rem(1_000_000_007), (Big::from_digits(false, vec![1218004076, 2501148443, 1266874760, 4]), 114944276));
+    assert!(a.mul(&a).divisible(u64::MAX) && !b.divisible(u64::MAX));
+    assert_eq!(Big::new(0).to_string(), "0");
+    assert_eq!(Big::new(1_000_000_000).relieve(3).to_string(), "333333333");
+}
+
+#[test]
+fn modes() {
+    assert_eq!(run1(EXAMPLE), 10605);
+    assert_eq!(run2(EXAMPLE), 2713310158);
+    assert_eq!(inspections(EXAMPLE, 20, 1, Mode::Modular), Ok(vec![99,97,8,103]));
+    for rounds in 1..=8 {
+        assert_eq!(inspections(EXAMPLE, rounds, 1, Mode::Exact), inspections(EXAMPLE, rounds, 1, Mode::Modular));
+    }
+    assert_eq!(monkey_business(EXAMPLE, 20, 3, Mode::Modular), Err("Worry levels must be exact to be relieved".to_string()));
+    assert!(monkey_business(EXAMPLE, 20, 0, Mode::Exact).is_err());
+    let zero = EXAMPLE.replace("by 13", "by 0");
+    for mode in [Mode::Exact, Mode::Modular] {
+        assert_eq!(monkey_business(&zero, 20, 1, mode), Err("Monkey 2 tests divisibility by 0".to_string()));
+    }
+    let huge = EXAMPLE.replace("by 23", "by 18446744073709551557").replace("by 19", "by 4294967291");
+    assert_eq!(monkey_business(&huge, 20, 1, Mode::Modular), Err("The test divisors' least common multiple overflows u64".to_string()));
+    assert_eq!(mcm(4, 6), Some(12));
+}
+
+#[test]
+fn expressions() {
+    let e = Expr::parse("old * old + 3 * (old - 20) ^ 2 - -7").unwrap();
+    assert_eq!(e.evaluate(&Big::new(5)).to_string(), "707");
+    assert_eq!(e.evaluate(&Big::new(5)).sub(&Big::new(1000)).to_string(), "-293");
+    assert_eq!(e.evaluate(&Residue { value: 5, modulus: 13 }).value, 707 % 13);
+    assert_eq!(Expr::parse("2^3^2").unwrap(), Expr::Pow(Box::new(Expr::Num(2)), 9));
+    assert_eq!(Expr::parse("-old^2").unwrap().evaluate(&Big::new(3)).to_string(), "-9");
+    assert_eq!(Expr::parse("old * 19"), Ok(Expr::Mul(Box::new(Expr::Old), Box::new(Expr::Num(19)))));
+    assert_eq!(Expr::parse("(old + 1"), Err("Expected )".to_string()));
+    assert_eq!(Expr::parse("old ^ old"), Err("Exponents must be natural numbers, not old".to_string()));
+    assert_eq!(Expr::parse("old / 2"), Err("Unexpected '/'".to_string()));
+    assert_eq!(Expr::parse("old 2"), Err("Unexpected 2".to_string()));
+    assert_eq!(Big::new(7).sub(&Big::new(10)).relieve(2).to_string(), "-1");
+    assert_eq!(Big::new(7).sub(&Big::new(10)).rem_euclid(5), 2);
+    // Worry levels going below zero give the same throws in both modes
+    let input = EXAMPLE.replace("old + 6", "(old - 90) * 3 - old ^ 2").replace("old * 19", "2 * old - 1");
+    for rounds in 1..=6 {
+        assert_eq!(inspections(&input, rounds, 1, Mode::Exact), inspections(&input, rounds, 1, Mode::Modular));
+    }
+}
+
+#[test]
+fn routes_repeat() {
+    for rounds in [0, 1, 2, 20, 137, 1000, 5000] {
+        assert_eq!(extrapolate(EXAMPLE, rounds), inspections(EXAMPLE, rounds as usize, 1, Mode::Modular));
+    }
+    assert_eq!(extrapolate(EXAMPLE, 10000).map(business), Ok(2713310158));
+    // Routes in the example repeat after 623 rounds at most
+    assert_eq!(extrapolate(EXAMPLE, 1_000_000_000_000), Ok(vec![5217653508757, 4782346491239, 193256578955, 5202028508760]));
+    let expected = "Round 1: 0 -> 3, worry 79\nRound 2: 1, worry 1504\nRepeats every 171 rounds from round 139\n";
+    assert_eq!(trace(EXAMPLE, 1, 2), Ok(expected.to_string()));
+    assert!(trace(EXAMPLE, 11, 2).is_err());
+    assert!(trace(EXAMPLE, 0, 2).is_err());
+}
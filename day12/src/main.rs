@@ -1,94 +1,253 @@
 use std::{env,fs,process};
-//use std::collections::HashMap;
-use std::collections::{HashSet,VecDeque};
+use std::collections::{VecDeque,BinaryHeap};
+use std::cmp::{Ordering,Reverse};
 
+// Elevation of a square: S is at a and E at z, as the puzzle says
 fn to_value(c: char) -> u32 {
     match c {
         'S' => 'a' as u32,
-        'E' => ('z' as u32) + 1,
+        'E' => 'z' as u32,
         _ => c as u32,
     }
 }
 
-fn neighbours(array: &Vec<Vec<char>>, x: usize, y: usize) -> Vec<(usize,usize)> {
-    let mut neighs = Vec::new();
-    if x > 0 {
-        if to_value(array[x][y]) + 1 >= to_value(array[x-1][y]) {
-            neighs.push((x-1,y));
+// Which steps are allowed: how much elevation can be gained or lost at once, with None for no
+// limit, and whether diagonal squares are adjacent too
+#[derive(Clone,Copy)]
+struct Rules {
+    max_up: Option<u32>,
+    max_down: Option<u32>,
+    diagonals: bool,
+}
+
+// At most one step up, any step down
+const PUZZLE: Rules = Rules { max_up: Some(1), max_down: None, diagonals: false };
+
+impl Rules {
+    fn allows(&self, from: u32, to: u32) -> bool {
+        if to > from {
+            self.max_up.is_none_or(|m| to - from <= m)
+        } else {
+            self.max_down.is_none_or(|m| from - to <= m)
         }
     }
+}
+
+// Cost of a step: a fixed part plus some per unit of elevation gained or lost
+#[derive(Clone,Copy)]
+struct Costs {
+    step: u64,
+    up: u64,
+    down: u64,
+}
+
+impl Costs {
+    fn of(&self, from: u32, to: u32) -> u64 {
+        self.step + self.up * to.saturating_sub(from) as u64 + self.down * from.saturating_sub(to) as u64
+    }
+}
+
+struct Heightmap {
+    array: Vec<Vec<char>>,
+    start: (usize,usize),
+    end: (usize,usize),
+}
 
-    if x < array.len() - 1 {
-        if to_value(array[x][y]) + 1 >= to_value(array[x+1][y]) {
-            neighs.push((x+1,y));
+impl Heightmap {
+    fn parse(input: &str) -> Result<Self, String> {
+        let array: Vec<Vec<char>> = input.lines().map(|line| line.trim().chars().collect()).collect();
+        let mut start = None;
+        let mut end = None;
+        for (n,row) in array.iter().enumerate() {
+            if row.len() != array[0].len() {
+                return Err(format!("Line {}: all rows must be as long as the first one", n + 1));
+            }
+            for (m,c) in row.iter().enumerate() {
+                match c {
+                    'S' => start = Some((n,m)),
+                    'E' => end = Some((n,m)),
+                    'a'..='z' => {},
+                    _ => return Err(format!("Line {}: unexpected {c:?}", n + 1)),
+                }
+            }
         }
+        match (start, end) {
+            (Some(start), Some(end)) => Ok(Self { array, start, end }),
+            _ => Err("The map needs a start S and an end E".to_string()),
+        }
+    }
+
+    fn height(&self, (x,y): (usize,usize)) -> u32 {
+        to_value(self.array[x][y])
     }
 
-    if y > 0 {
-        if to_value(array[x][y]) + 1 >= to_value(array[x][y-1]) {
-            neighs.push((x,y-1));
+    fn adjacent(&self, x: usize, y: usize, diagonals: bool) -> Vec<(usize,usize)> {
+        let mut adj = Vec::new();
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if (dx,dy) == (0,0) || (!diagonals && dx != 0 && dy != 0) {
+                    continue;
+                }
+                let (i,j) = (x as isize + dx, y as isize + dy);
+                if i >= 0 && j >= 0 && (i as usize) < self.array.len() && (j as usize) < self.array[0].len() {
+                    adj.push((i as usize,j as usize));
+                }
+            }
         }
+        adj
     }
 
-    if y < array[x].len()-1 {
-        if to_value(array[x][y]) + 1 >= to_value(array[x][y+1]) {
-            neighs.push((x,y+1));
+    // Squares from which we can step to (x,y) following the rules
+    fn climbers(&self, x: usize, y: usize, rules: &Rules) -> Vec<(usize,usize)> {
+        self.adjacent(x, y, rules.diagonals).into_iter()
+            .filter(|&p| rules.allows(self.height(p), self.height((x,y))))
+            .collect()
+    }
+}
+
+// Steps, or cost, to E from every square that can reach it, and the first of them
+struct Climb {
+    distance: Vec<Vec<Option<u64>>>,
+    next: Vec<Vec<Option<(usize,usize)>>>,
+}
+
+impl Climb {
+    // The squares from (x,y) to E, both included
+    fn path(&self, x: usize, y: usize) -> Option<Vec<(usize,usize)>> {
+        self.distance[x][y]?;
+        let mut path = vec![(x,y)];
+        while let Some(n) = self.next[path.last().unwrap().0][path.last().unwrap().1] {
+            path.push(n);
         }
+        Some(path)
     }
-    neighs
-}
-
-//fn depth_first_search(x0: usize, y0: usize, array: &Vec<Vec<char>>) -> Option<u32> {
-    //let mut stack = Vec::new();
-    //// Stack contains x, y and depth
-    //stack.push((x0,y0,0));
-    //let mut visited = HashSet::new();
-    //while let Some(xyd) = stack.pop(){
-        //println!("In node ({},{}) with depth {}", xyd.0, xyd.1, xyd.2);
-        ////Check if discovered
-        //if !visited.contains(&(xyd.0,xyd.1)) {
-            ////println!("Node ({},{}) not visited", xyd.0, xyd.1);
-            ////Check if we are done!
-            //if array[xyd.0][xyd.1] == 'E' {
-                //return Some(xyd.2);
-            //}
-
-            ////Mark as discovered
-            //visited.insert((xyd.0,xyd.1));
-
-            ////Add neighbours to be visited
-            //for n in neighbours(array,xyd.0,xyd.1) {
-                ////println!("I'm a friend!");
-                //stack.push((n.0,n.1,xyd.2+1));
-            //}
-        //}
-    //}
-    //None
-//}
-
-fn breadth_first_search(x0: usize, y0: usize, array: &Vec<Vec<char>>) -> Option<u32> {
+}
+
+// A single search going backwards from E reaches every square that can get there, so it answers
+// for any starting point at once
+fn breadth_first_search(map: &Heightmap, rules: &Rules) -> Climb {
+    let mut distance = vec![vec![None; map.array[0].len()]; map.array.len()];
+    let mut next = vec![vec![None; map.array[0].len()]; map.array.len()];
     let mut queue = VecDeque::new();
     // Queue contains x, y and depth
-    queue.push_back((x0,y0,0));
-    let mut visited = HashSet::new();
-    visited.insert((x0,y0));
-    while let Some(xyd) = queue.pop_front(){
-        println!("In node ({},{}) with depth {}", xyd.0, xyd.1, xyd.2);
-
-        //Check if we are done!
-        if array[xyd.0][xyd.1] == 'E' {
-            return Some(xyd.2);
+    queue.push_back((map.end.0,map.end.1,0));
+    distance[map.end.0][map.end.1] = Some(0);
+    while let Some((x,y,d)) = queue.pop_front() {
+        for (i,j) in map.climbers(x,y,rules) {
+            if distance[i][j].is_none() {
+                distance[i][j] = Some(d + 1);
+                next[i][j] = Some((x,y));
+                queue.push_back((i,j,d + 1));
+            }
         }
+    }
+    Climb { distance, next }
+}
 
-        //Add neighbours to be visited
-        for n in neighbours(array,xyd.0,xyd.1) {
-            if !visited.contains(&(n.0,n.1)) {
-                visited.insert((n.0,n.1));
-                queue.push_back((n.0,n.1,xyd.2 + 1));
+// Same as breadth_first_search, with steps costing differently
+fn dijkstra(map: &Heightmap, rules: &Rules, costs: &Costs) -> Climb {
+    let mut distance = vec![vec![None; map.array[0].len()]; map.array.len()];
+    let mut next = vec![vec![None; map.array[0].len()]; map.array.len()];
+    let mut heap = BinaryHeap::new();
+    heap.push(Reverse((0,map.end)));
+    distance[map.end.0][map.end.1] = Some(0);
+    while let Some(Reverse((d,(x,y)))) = heap.pop() {
+        if distance[x][y].is_some_and(|best| best < d) {
+            continue;
+        }
+        for (i,j) in map.climbers(x,y,rules) {
+            let c = d + costs.of(map.height((i,j)), map.height((x,y)));
+            if distance[i][j].is_none_or(|best| c < best) {
+                distance[i][j] = Some(c);
+                next[i][j] = Some((x,y));
+                heap.push(Reverse((c,(i,j))));
             }
         }
     }
-    None
+    Climb { distance, next }
+}
+
+// The square at the lowest elevation with the shortest path to E
+fn best_start(map: &Heightmap, climb: &Climb) -> Option<(usize,usize)> {
+    let mut best = None;
+    for (i,row) in map.array.iter().enumerate() {
+        for (j,c) in row.iter().enumerate() {
+            if to_value(*c) == 'a' as u32 {
+                if let Some(d) = climb.distance[i][j] {
+                    if best.is_none_or(|(b,_)| d < b) {
+                        best = Some((d,(i,j)));
+                    }
+                }
+            }
+        }
+    }
+    best.map(|(_,p)| p)
+}
+
+// The map with the path drawn over it, each square pointing to the next one as in the puzzle.
+// Diagonal steps are drawn as / and \
+fn render(map: &Heightmap, path: &[(usize,usize)]) -> String {
+    let mut drawing = vec![vec!['.'; map.array[0].len()]; map.array.len()];
+    for w in path.windows(2) {
+        let ((x0,y0),(x1,y1)) = (w[0],w[1]);
+        drawing[x0][y0] = match (x1.cmp(&x0), y1.cmp(&y0)) {
+            (Ordering::Greater, Ordering::Equal) => 'v',
+            (Ordering::Less, Ordering::Equal) => '^',
+            (Ordering::Equal, Ordering::Greater) => '>',
+            (Ordering::Equal, _) => '<',
+            (Ordering::Greater, Ordering::Less) | (Ordering::Less, Ordering::Greater) => '/',
+            _ => '\\',
+        };
+    }
+    if let Some(&(x,y)) = path.last() {
+        drawing[x][y] = 'E';
+    }
+    drawing.iter().map(|row| row.iter().collect::<String>() + "\n").collect()
+}
+
+fn solve(input: &str) -> (Heightmap, Climb) {
+    let map = Heightmap::parse(input).unwrap_or_else(|e| panic!("{e}"));
+    let climb = breadth_first_search(&map, &PUZZLE);
+    (map, climb)
+}
+
+// Options given as up=N, down=N, diagonal, step=N, climb=N and descent=N. Unless some cost is
+// given every step costs 1
+fn options<'a>(args: impl Iterator<Item = &'a str>) -> Result<(Rules, Option<Costs>), String> {
+    let mut rules = PUZZLE;
+    let mut costs = None;
+    for arg in args {
+        if arg == "diagonal" {
+            rules.diagonals = true;
+            continue;
+        }
+        let (key, value) = arg.split_once('=').ok_or(format!("Unknown option {arg}"))?;
+        let n: u32 = value.parse().map_err(|_| format!("{key} must be a number, not {value}"))?;
+        match key {
+            "up" => rules.max_up = Some(n),
+            "down" => rules.max_down = Some(n),
+            "step" | "climb" | "descent" => {
+                let c = costs.get_or_insert(Costs { step: 1, up: 0, down: 0 });
+                match key {
+                    "step" => c.step = n as u64,
+                    "climb" => c.up = n as u64,
+                    _ => c.down = n as u64,
+                }
+            },
+            _ => return Err(format!("Unknown option {arg}")),
+        }
+    }
+    Ok((rules, costs))
+}
+
+fn run1(input: &str) -> Option<u64> {
+    let (map, climb) = solve(input);
+    climb.distance[map.start.0][map.start.1]
+}
+
+fn run2(input: &str) -> Option<u64> {
+    let (map, climb) = solve(input);
+    best_start(&map, &climb).and_then(|(i,j)| climb.distance[i][j])
 }
 
 fn main() {
@@ -104,53 +263,114 @@ fn main() {
     }
 
     let input = fs::read_to_string(filepath).unwrap();
-    let mut array : Vec<Vec<char>> = Vec::new();
-    let mut n = 0;
-    let mut start_position = (0,0);
-    for line in input.lines() {
-        let mut m = 0;
-        let mut row = Vec::new();
-        for c in line.chars() {
-            if c=='S' {
-                start_position = (n,m);
+
+    let result = match args.next().as_deref() {
+        Some("start") => run1(&input),
+        // Draw the path from S, or from the best starting square with "hike"
+        Some(mode @ ("path" | "hike")) => {
+            let (map, climb) = solve(&input);
+            let from = if mode == "path" { Some(map.start) } else { best_start(&map, &climb) };
+            match from.and_then(|(i,j)| climb.path(i,j)) {
+                Some(path) => {
+                    print!("{}", render(&map, &path));
+                    Some(path.len() as u64 - 1)
+                },
+                None => None,
             }
-            row.push(c);
-            m += 1;
-        }
-        array.push(row);
-        n += 1;
-    }
-
-
-    // First part
-    //let result = breadth_first_search(start_position.0,start_position.1,&array);
-    //match result {
-        //Some(res) => {
-            //println!("{res}");
-        //}
-        //None => {
-            //println!("Not found");
-        //}
-    //}
-
-    // Second part
-    let mut best_result = 1000000;
-    for i in 0..array.len() {
-        for j in 0..array[i].len() {
-            if array[i][j] == 'S' || array[i][j] == 'a' {
-                let result = breadth_first_search(i,j,&array);
-                match result {
-                    Some(res) => {
-                        if res < best_result {
-                            best_result = res;
-                        }
+        },
+        // Paths from S and from the best starting square under other rules and costs
+        Some("plan") => {
+            let opts: Vec<String> = args.collect();
+            let (rules, costs) = options(opts.iter().map(|s| s.as_str())).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                process::exit(1);
+            });
+            let map = Heightmap::parse(&input).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                process::exit(1);
+            });
+            let climb = match costs {
+                Some(c) => dijkstra(&map, &rules, &c),
+                None => breadth_first_search(&map, &rules),
+            };
+            for (name,from) in [("From S", Some(map.start)), ("Best start", best_start(&map, &climb))] {
+                match from.and_then(|(i,j)| climb.path(i,j)) {
+                    Some(path) => {
+                        print!("{}", render(&map, &path));
+                        println!("{name}: {}", climb.distance[path[0].0][path[0].1].unwrap());
                     },
-                    None => {
-                        println!("Not found!");
-                    }
+                    None => println!("{name}: not found!"),
                 }
             }
-        }
+            return;
+        },
+        _ => run2(&input),
+    };
+    match result {
+        Some(res) => println!("Best path: {res}"),
+        None => println!("Not found!"),
     }
-    println!("Best path: {}", best_result);
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "Sabqponm
+abcryxxl
+accszExk
+acctuvwj
+abdefghi
+";
+
+#[test]
+fn example() {
+    assert_eq!(run1(EXAMPLE), Some(31));
+    assert_eq!(run2(EXAMPLE), Some(29));
+    let (map, climb) = solve(EXAMPLE);
+    let path = climb.path(0,0).unwrap();
+    assert_eq!(path.len(), 32);
+    let expected = "\
+>>vv<<<<
+..vvv<<^
+..vv>E^^
+..v>>>^^
+..>>>>>^
+";
+    assert_eq!(render(&map, &path), expected);
+    assert_eq!(best_start(&map, &climb), Some((4,0)));
+    assert!(Heightmap::parse("Sab\nbE").is_err());
+    assert!(Heightmap::parse("Sab\nbcd").is_err());
+    assert_eq!(run1("SbcE\n"), None);
+}
+
+#[test]
+fn rules_and_costs() {
+    let map = Heightmap::parse(EXAMPLE).unwrap();
+    let unit = Costs { step: 1, up: 0, down: 0 };
+    let bfs = breadth_first_search(&map, &PUZZLE);
+    assert_eq!(dijkstra(&map, &PUZZLE, &unit).distance, bfs.distance);
+    // Going around the spiral diagonally saves some steps
+    let diagonal = Rules { diagonals: true, ..PUZZLE };
+    let climb = breadth_first_search(&map, &diagonal);
+    assert_eq!(climb.distance[0][0], Some(27));
+    assert_eq!(render(&map, &climb.path(0,0).unwrap()), "\\..v<<<<\n.v.v\\<.^\n.v.v.E\\^\n.\\.>>>^^\n..>>>>>^\n");
+    // The spiral only goes up, so limiting descents changes nothing
+    let careful = Rules { max_down: Some(1), ..PUZZLE };
+    assert_eq!(breadth_first_search(&map, &careful).distance[0][0], bfs.distance[0][0]);
+    let steep = Rules { max_up: None, ..PUZZLE };
+    let peaks = Heightmap::parse("SzbzE\n").unwrap();
+    assert_eq!(breadth_first_search(&peaks, &steep).distance[0][0], Some(4));
+    assert_eq!(breadth_first_search(&peaks, &Rules { max_down: Some(1), ..steep }).distance[0][0], None);
+    assert_eq!(breadth_first_search(&peaks, &PUZZLE).distance[0][0], None);
+    // Climbing and descending cost 10 per unit, so the cheaper path goes around the wall
+    let wall = Heightmap::parse("Sazaa\naazaa\naaaaE\n").unwrap();
+    let climb = dijkstra(&wall, &steep, &Costs { step: 1, up: 10, down: 10 });
+    assert_eq!(climb.distance[0][0], Some(6 + 10 * 25));
+    assert_eq!(render(&wall, &climb.path(0,0).unwrap()), ">v...\n.v...\n.>>>E\n");
+    // Without costs or limits it goes straight over the wall
+    let quick = dijkstra(&wall, &Rules { diagonals: true, ..steep }, &unit);
+    assert_eq!(quick.distance[0][0], Some(4));
+    assert_eq!(render(&wall, &quick.path(0,0).unwrap()), ">>\\..\n...\\.\n....E\n");
+    assert!(options(["up=2", "diagonal"].into_iter()).is_ok_and(|(r,c)| r.max_up == Some(2) && r.diagonals && c.is_none()));
+    assert!(options(["climb=3"].into_iter()).is_ok_and(|(_,c)| c.is_some_and(|c| c.up == 3 && c.step == 1)));
+    assert!(options(["fly"].into_iter()).is_err());
+    assert!(options(["up=x"].into_iter()).is_err());
 }
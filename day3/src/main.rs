@@ -1,41 +1,125 @@
 use std::{env,process,fs};
+use std::collections::{BTreeSet,HashMap};
 
-fn run1(input: &str) -> u32 {
-    let mut sum = 0;
-    for line in input.lines() {
-        let (l,r) = line.split_at(line.len() / 2);
-        for cl in l.chars() {
-            if r.contains(cl) {
-                if cl.is_lowercase() {
-                    sum += cl as u32 - 'a' as u32 + 1;
-                } else {
-                    sum += cl as u32 - 'A' as u32 + 27;
-                }
-                break;
-            }
-        }
+fn priority(c: char) -> u32 {
+    if c.is_lowercase() {
+        c as u32 - 'a' as u32 + 1
+    } else {
+        c as u32 - 'A' as u32 + 27
     }
-    sum
 }
 
-fn run2(input: &str) -> u32 {
-    let mut sum = 0;
+// Items appearing in both compartments of a rucksack, sorted and without repetitions
+fn misplaced(line: &str) -> BTreeSet<char> {
+    let (l,r) = line.split_at(line.len() / 2);
+    l.chars().filter(|c| r.contains(*c)).collect()
+}
+
+// First item of the left compartment that is also in the right one
+fn first_misplaced(line: &str) -> Option<char> {
+    let (l,r) = line.split_at(line.len() / 2);
+    l.chars().find(|c| r.contains(*c))
+}
+
+// The only item carried by the three elves of a group, if any
+fn badge(group: &[&str]) -> Option<char> {
+    group[0].chars().find(|c| group[1..].iter().all(|s| s.contains(*c)))
+}
+
+fn format_items(items: &BTreeSet<char>) -> String {
+    if items.is_empty() {
+        String::from("-")
+    } else {
+        items.iter().collect()
+    }
+}
+
+fn report(input: &str) -> String {
     let lines: Vec<&str> = input.lines().collect();
-    let mut i = 0;
-    while i < lines.len() {
-        for c in lines[i].chars() {
-            if lines[i+1].contains(c) && lines[i+2].contains(c) {
-                if c.is_lowercase() {
-                    sum += c as u32 - 'a' as u32 + 1;
-                } else {
-                    sum += c as u32 - 'A' as u32 + 27;
+    let mut out = String::new();
+    for (i,line) in lines.iter().enumerate() {
+        out.push_str(&format!("Rucksack {}: misplaced {}\n", i + 1, format_items(&misplaced(line))));
+    }
+    for (g,group) in lines.chunks(3).enumerate() {
+        let b = match badge(group) {
+            Some(c) => c.to_string(),
+            None => String::from("-"),
+        };
+        out.push_str(&format!("Group {}: badge {b}\n", g + 1));
+    }
+    out.push_str(&format!("Items to move: {}\n", format_items(&items_to_move(input))));
+    out.push_str(&format!("Misplaced priorities: {}\n", run1(input)));
+    out.push_str(&format!("Badge priorities: {}\n", run2(input)));
+    out
+}
+
+// Every item type that needs to move between compartments in at least one rucksack
+fn items_to_move(input: &str) -> BTreeSet<char> {
+    input.lines().flat_map(misplaced).collect()
+}
+
+// Rearrange the items of a rucksack so that every item type ends up in a single compartment,
+// keeping both compartments of the same size and moving as few items as possible
+fn fix_line(line: &str) -> Result<String, String> {
+    let half = line.len() / 2;
+    let (l,r) = line.split_at(half);
+    if misplaced(line).is_empty() {
+        return Ok(line.to_string());
+    }
+    let types: BTreeSet<char> = line.chars().collect();
+    // Subset sum over the item types: best[k] holds the least number of moves that leaves k items
+    // in the left compartment, together with the types sent there
+    let mut best: HashMap<usize, (usize, Vec<char>)> = HashMap::new();
+    best.insert(0, (0, vec![]));
+    for c in types {
+        let nl = l.chars().filter(|x| *x == c).count();
+        let nr = r.chars().filter(|x| *x == c).count();
+        let mut next: HashMap<usize, (usize, Vec<char>)> = HashMap::new();
+        for (k,(moves,left_types)) in best {
+            let mut with_c = left_types.clone();
+            with_c.push(c);
+            for (key,val) in [(k + nl + nr, (moves + nr, with_c)), (k, (moves + nl, left_types))] {
+                if key > half {
+                    continue;
+                }
+                match next.get(&key) {
+                    Some((m,_)) if *m <= val.0 => {},
+                    _ => { next.insert(key,val); },
                 }
-                break;
             }
         }
-        i += 3;
+        best = next;
     }
-    sum
+    match best.get(&half) {
+        Some((_,left_types)) => {
+            let goes_left = |c: &char| left_types.contains(c);
+            let mut left: String = l.chars().filter(goes_left).collect();
+            let mut right: String = r.chars().filter(|c| !goes_left(c)).collect();
+            left.extend(r.chars().filter(goes_left));
+            right.extend(l.chars().filter(|c| !goes_left(c)));
+            Ok(left + &right)
+        },
+        None => Err(format!("Rucksack {line} cannot be split into two compartments of size {half}")),
+    }
+}
+
+fn fix(input: &str) -> Result<String, String> {
+    let mut out = String::new();
+    for (i,line) in input.lines().enumerate() {
+        let fixed = fix_line(line).map_err(|e| format!("Line {}: {e}", i + 1))?;
+        out.push_str(&fixed);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn run1(input: &str) -> u32 {
+    input.lines().filter_map(first_misplaced).map(priority).sum()
+}
+
+fn run2(input: &str) -> u32 {
+    let lines: Vec<&str> = input.lines().collect();
+    lines.chunks(3).filter_map(badge).map(priority).sum()
 }
 
 fn main() {
@@ -52,8 +136,22 @@ fn main() {
 
     let input = fs::read_to_string(filepath).unwrap();
 
-    let res = run2(&input);
-    println!("{res}");
+    match args.next().as_deref() {
+        Some("report") => print!("{}", report(&input)),
+        Some("fix") => {
+            match fix(&input) {
+                Ok(s) => print!("{s}"),
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                },
+            }
+        },
+        _ => {
+            let res = run2(&input);
+            println!("{res}");
+        },
+    }
 }
 
 #[test]
@@ -83,3 +181,25 @@ fn input2() {
     let res = run2(&input);
     assert_eq!(res, 2522);
 }
+
+#[test]
+fn fix_example() {
+    let input = "vJrwpWtwJgWrhcsFMMfFFhFp\njqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL\nPmmdzqPrVvPwwTWBwg\n";
+    let fixed = fix(input).unwrap();
+    assert!(items_to_move(&fixed).is_empty());
+    for (a,b) in input.lines().zip(fixed.lines()) {
+        let mut a: Vec<char> = a.chars().collect();
+        let mut b: Vec<char> = b.chars().collect();
+        a.sort();
+        b.sort();
+        assert_eq!(a,b);
+    }
+    assert!(fix("aaab\n").is_err());
+}
+
+#[test]
+fn first_match() {
+    assert_eq!(run1("abab\n"), 1);
+    assert_eq!(run1("baab\n"), 2);
+    assert_eq!(run1("abcd\n"), 0);
+}
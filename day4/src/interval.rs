@@ -0,0 +1,233 @@
+// Closed intervals [start, end] over a discrete ordered set of points
+
+use std::collections::BTreeSet;
+
+pub trait Point: Ord + Copy {
+    // Next and previous points. Only called when they are known to exist
+    fn succ(self) -> Self;
+    fn pred(self) -> Self;
+    // Number of points in [a, b], assuming a <= b
+    fn count(a: Self, b: Self) -> u64;
+}
+
+macro_rules! impl_point {
+    ($($t:ty),*) => {
+        $(impl Point for $t {
+            fn succ(self) -> Self {
+                self + 1
+            }
+
+            fn pred(self) -> Self {
+                self - 1
+            }
+
+            fn count(a: Self, b: Self) -> u64 {
+                (b as i128 - a as i128 + 1) as u64
+            }
+        })*
+    };
+}
+
+impl_point!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub struct Interval<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: Point> Interval<T> {
+    pub fn new(start: T, end: T) -> Self {
+        assert!(start <= end, "An interval must start before it ends");
+        Self { start, end }
+    }
+
+    pub fn start(&self) -> T {
+        self.start
+    }
+
+    pub fn end(&self) -> T {
+        self.end
+    }
+
+    pub fn len(&self) -> u64 {
+        T::count(self.start, self.end)
+    }
+
+    #[allow(dead_code)]
+    pub fn contains_point(&self, p: T) -> bool {
+        self.start <= p && p <= self.end
+    }
+
+    pub fn contains(&self, other: &Self) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    // True if both intervals overlap or one starts right after the other ends
+    #[allow(dead_code)]
+    pub fn touches(&self, other: &Self) -> bool {
+        self.overlaps(other)
+            || (self.end < other.start && self.end.succ() == other.start)
+            || (other.end < self.start && other.end.succ() == self.start)
+    }
+
+    #[allow(dead_code)]
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        if self.overlaps(other) {
+            Some(Self::new(self.start.max(other.start), self.end.min(other.end)))
+        } else {
+            None
+        }
+    }
+
+    // The union is only an interval when both intervals touch
+    #[allow(dead_code)]
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        if self.touches(other) {
+            Some(Self::new(self.start.min(other.start), self.end.max(other.end)))
+        } else {
+            None
+        }
+    }
+
+    // Points of self not in other, as zero, one or two intervals
+    #[allow(dead_code)]
+    pub fn difference(&self, other: &Self) -> Vec<Self> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+        let mut res = Vec::new();
+        if self.start < other.start {
+            res.push(Self::new(self.start, other.start.pred()));
+        }
+        if other.end < self.end {
+            res.push(Self::new(other.end.succ(), self.end));
+        }
+        res
+    }
+}
+
+// Sorted list of disjoint intervals covering the same points as the given ones
+pub fn merge_overlapping<T: Point>(intervals: &[Interval<T>]) -> Vec<Interval<T>> {
+    let mut sorted = intervals.to_vec();
+    sorted.sort();
+    let mut merged: Vec<Interval<T>> = Vec::new();
+    for i in sorted {
+        match merged.last_mut() {
+            Some(last) if last.overlaps(&i) => {
+                last.end = last.end.max(i.end);
+            },
+            _ => merged.push(i),
+        }
+    }
+    merged
+}
+
+// Number of points covered by at least one interval
+pub fn total_len<T: Point>(intervals: &[Interval<T>]) -> u64 {
+    merge_overlapping(intervals).iter().map(|i| i.len()).sum()
+}
+
+// Disjoint intervals whose points are covered by more than k of the given intervals
+pub fn covered_more_than<T: Point>(intervals: &[Interval<T>], k: usize) -> Vec<Interval<T>> {
+    // Openings sort before closings at the same point, since intervals are closed
+    let mut events: Vec<(T, bool)> = Vec::with_capacity(2 * intervals.len());
+    for i in intervals {
+        events.push((i.start, false));
+        events.push((i.end, true));
+    }
+    events.sort();
+    let mut res = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+    for (p, closing) in events {
+        if closing {
+            if depth == k + 1 {
+                res.push(Interval::new(start.take().unwrap(), p));
+            }
+            depth -= 1;
+        } else {
+            depth += 1;
+            if depth == k + 1 {
+                start = Some(p);
+            }
+        }
+    }
+    merge_overlapping(&res)
+}
+
+pub struct Sweep<T> {
+    // Largest number of intervals sharing a point
+    pub max_depth: usize,
+    // Uncovered stretches between the first and the last covered point
+    pub gaps: Vec<Interval<T>>,
+    // Indices (i, j), i < j, of every pair of overlapping intervals
+    pub overlaps: Vec<(usize,usize)>,
+}
+
+// Single pass over the sorted endpoints of all intervals: O(n log n) plus the number of
+// overlapping pairs reported
+pub fn sweep<T: Point>(intervals: &[Interval<T>]) -> Sweep<T> {
+    let mut events: Vec<(T, bool, usize)> = Vec::with_capacity(2 * intervals.len());
+    for (n, i) in intervals.iter().enumerate() {
+        events.push((i.start, false, n));
+        events.push((i.end, true, n));
+    }
+    events.sort();
+    let mut active: BTreeSet<usize> = BTreeSet::new();
+    let mut res = Sweep { max_depth: 0, gaps: vec![], overlaps: vec![] };
+    let mut last_end: Option<T> = None;
+    for (p, closing, n) in events {
+        if closing {
+            active.remove(&n);
+            if active.is_empty() {
+                last_end = Some(p);
+            }
+        } else {
+            if let Some(e) = last_end.take() {
+                if e.succ() < p {
+                    res.gaps.push(Interval::new(e.succ(), p.pred()));
+                }
+            }
+            for m in &active {
+                res.overlaps.push((*m.min(&n), *m.max(&n)));
+            }
+            active.insert(n);
+            res.max_depth = res.max_depth.max(active.len());
+        }
+    }
+    res.overlaps.sort();
+    res
+}
+
+#[test]
+fn operations() {
+    let a = Interval::new(2u32, 8);
+    let b = Interval::new(6u32, 10);
+    assert_eq!(a.intersection(&b), Some(Interval::new(6, 8)));
+    assert_eq!(a.union(&b), Some(Interval::new(2, 10)));
+    assert_eq!(a.union(&Interval::new(9, 12)), Some(Interval::new(2, 12)));
+    assert_eq!(a.union(&Interval::new(10, 12)), None);
+    assert_eq!(a.difference(&Interval::new(4, 5)), vec![Interval::new(2, 3), Interval::new(6, 8)]);
+    assert_eq!(a.difference(&Interval::new(0, 20)), vec![]);
+    assert!(a.contains(&Interval::new(3, 7)));
+    assert!(a.contains_point(8) && !a.contains_point(9));
+    assert_eq!(Interval::new(0u8, 255).len(), 256);
+}
+
+#[test]
+fn coverage() {
+    let v = vec![Interval::new(2u32, 4), Interval::new(6, 8), Interval::new(3, 7), Interval::new(20, 20)];
+    assert_eq!(merge_overlapping(&v), vec![Interval::new(2, 8), Interval::new(20, 20)]);
+    assert_eq!(total_len(&v), 8);
+    assert_eq!(covered_more_than(&v, 1), vec![Interval::new(3, 4), Interval::new(6, 7)]);
+    assert_eq!(covered_more_than(&v, 2), vec![]);
+    let s = sweep(&v);
+    assert_eq!(s.max_depth, 2);
+    assert_eq!(s.gaps, vec![Interval::new(9, 19)]);
+    assert_eq!(s.overlaps, vec![(0, 2), (1, 2)]);
+}
@@ -1,34 +1,74 @@
 use std::{env,fs,process};
+use std::collections::BTreeSet;
 
-fn contains(a: (u8,u8), b: (u8,u8)) -> bool {
-    a.0 <= b.0 && b.1 <= a.1
+mod interval;
+
+use interval::Interval;
+
+// The assignments of the two elves of a line
+type Pair = (Interval<u32>,Interval<u32>);
+
+fn read_interval(s: &str) -> Result<Interval<u32>, String> {
+    let (a,b) = s.split_once('-').ok_or(format!("Assignment {s:?} should look like 2-4"))?;
+    let a = a.parse::<u32>().map_err(|e| format!("Assignment {s:?}: {e}"))?;
+    let b = b.parse::<u32>().map_err(|e| format!("Assignment {s:?}: {e}"))?;
+    if a > b {
+        return Err(format!("Assignment {s:?} ends before it starts"));
+    }
+    Ok(Interval::new(a, b))
 }
 
-fn read_line(line: &str) -> ((u8,u8),(u8,u8)) {
-    let v: Vec<u8> = line.split(&['-', ',']).map(|s| s.parse::<u8>().unwrap()).collect();
-    ((v[0], v[1]), (v[2], v[3]))
+fn read_line(line: &str) -> Result<Pair, String> {
+    let (a,b) = line.split_once(',').ok_or(format!("Expected two assignments, got {line:?}"))?;
+    Ok((read_interval(a)?, read_interval(b)?))
 }
 
-fn run1(input: &str) -> u16 {
-    let mut num = 0;
-    for line in input.lines() {
-        let (a,b) = read_line(line.trim());
-        if contains(a,b) || contains(b,a) {
-            num += 1;
-        }
-    }
-    num
+// Both assignments of every line, with the number of the line in the error if one is malformed
+fn read_pairs(input: &str) -> Result<Vec<Pair>, String> {
+    input.lines().enumerate()
+        .map(|(k,line)| read_line(line.trim()).map_err(|e| format!("Line {}: {e}", k + 1)))
+        .collect()
+}
+
+// Every assignment in the file, regardless of the pair it belongs to
+fn read_all(input: &str) -> Result<Vec<Interval<u32>>, String> {
+    Ok(read_pairs(input)?.into_iter().flat_map(|(a,b)| [a,b]).collect())
 }
 
-fn run2(input: &str) -> u16 {
-    let mut num = 0;
-    for line in input.lines() {
-        let (a,b) = read_line(line.trim());
-        if a.0 <= b.1 && b.0 <= a.1 {
-            num += 1;
-        }
+// Number of sections assigned to at least one elf
+fn covered(input: &str) -> Result<u64, String> {
+    Ok(interval::total_len(&read_all(input)?))
+}
+
+// Number of sections assigned to more than k elves
+fn covered_more_than(input: &str, k: usize) -> Result<u64, String> {
+    Ok(interval::covered_more_than(&read_all(input)?, k).iter().map(|i| i.len()).sum())
+}
+
+// Depth, gaps and overlapping lines considering all assignments together. Lines are numbered
+// from 1, as in the input file
+fn sweep_report(input: &str) -> Result<String, String> {
+    let s = interval::sweep(&read_all(input)?);
+    let mut out = format!("Maximum depth: {}\n", s.max_depth);
+    let gaps: Vec<String> = s.gaps.iter().map(|g| format!("{}-{}", g.start(), g.end())).collect();
+    out.push_str(&format!("Gaps: {}\n", gaps.join(",")));
+    // Both assignments of a line come one after the other in read_all
+    let pairs: BTreeSet<(usize,usize)> = s.overlaps.iter()
+        .map(|(i,j)| (i.min(j) / 2 + 1, i.max(j) / 2 + 1))
+        .filter(|(i,j)| i != j)
+        .collect();
+    for (i,j) in pairs {
+        out.push_str(&format!("Lines {i} and {j} overlap\n"));
     }
-    num
+    Ok(out)
+}
+
+fn run1(input: &str) -> Result<u16, String> {
+    Ok(read_pairs(input)?.iter().filter(|(a,b)| a.contains(b) || b.contains(a)).count() as u16)
+}
+
+fn run2(input: &str) -> Result<u16, String> {
+    Ok(read_pairs(input)?.iter().filter(|(a,b)| a.overlaps(b)).count() as u16)
 }
 
 fn main() {
@@ -45,34 +85,79 @@ fn main() {
 
     let input = fs::read_to_string(filepath).unwrap();
 
-    let res = run2(&input);
-    println!("{res}");
+    let res = match args.next().as_deref() {
+        Some("contained") => run1(&input).map(|n| format!("{n}\n")),
+        Some("sweep") => sweep_report(&input),
+        Some("covered") => covered(&input).map(|n| format!("{n}\n")),
+        Some("more") => {
+            let k = args.next().and_then(|s| s.parse::<usize>().ok()).unwrap_or_else(|| {
+                eprintln!("How many elves? Give me a number!");
+                process::exit(1);
+            });
+            covered_more_than(&input, k).map(|n| format!("{n}\n"))
+        },
+        _ => run2(&input).map(|n| format!("{n}\n")),
+    };
+    match res {
+        Ok(s) => print!("{s}"),
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        },
+    }
 }
 
 #[test]
 fn example1() {
     let input = fs::read_to_string("test.txt").unwrap();
     let res = run1(&input);
-    assert_eq!(res,2);
+    assert_eq!(res,Ok(2));
 }
 
 #[test]
 fn input1() {
     let input = fs::read_to_string("input.txt").unwrap();
     let res = run1(&input);
-    assert_eq!(res,424);
+    assert_eq!(res,Ok(424));
 }
 
 #[test]
 fn example2() {
     let input = fs::read_to_string("test.txt").unwrap();
     let res = run2(&input);
-    assert_eq!(res,4);
+    assert_eq!(res,Ok(4));
 }
 
 #[test]
 fn input2() {
     let input = fs::read_to_string("input.txt").unwrap();
     let res = run2(&input);
-    assert_eq!(res,804);
+    assert_eq!(res,Ok(804));
+}
+
+#[test]
+fn coverage() {
+    let input = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8\n300-400,350-360\n";
+    assert_eq!(run1(input), Ok(3));
+    assert_eq!(run2(input), Ok(5));
+    assert_eq!(covered(input), Ok(8 + 101));
+    assert_eq!(covered_more_than(input, 5), Ok(4));
+}
+
+#[test]
+fn sweep_lines() {
+    let input = "2-4,6-8\n10-12,13-13\n7-7,20-30\n";
+    let expected = "Maximum depth: 2\nGaps: 5-5,9-9,14-19\nLines 1 and 3 overlap\n";
+    assert_eq!(sweep_report(input).as_deref(), Ok(expected));
+    let input = "1-2,1-2\n1-2,50-50\n1-2,60-60\n";
+    let expected = "Maximum depth: 4\nGaps: 3-49,51-59\nLines 1 and 2 overlap\nLines 1 and 3 overlap\nLines 2 and 3 overlap\n";
+    assert_eq!(sweep_report(input).as_deref(), Ok(expected));
+}
+
+#[test]
+fn bad_lines() {
+    assert_eq!(run1("2-4,6-8\n5-3,1-2\n"), Err("Line 2: Assignment \"5-3\" ends before it starts".to_string()));
+    assert!(run2("2-4\n").unwrap_err().starts_with("Line 1: Expected two assignments"));
+    assert!(covered("2-4,6-x\n").unwrap_err().starts_with("Line 1: Assignment \"6-x\""));
+    assert!(sweep_report("2-4,6\n").is_err());
 }
@@ -1,111 +1,279 @@
-use std::{env,fs,process};
+use std::{env,fmt,fs,process};
 use std::collections::VecDeque;
 
-fn run1(input: &str) -> String {
-    let mut stacks = Vec::new();
-    let mut lines = input.lines();
-    while let Some(line) = lines.next() {
-        if line.contains(|c: char| c.is_ascii_digit()) {
-            break;
+#[derive(Debug,Clone,PartialEq)]
+struct Stacks<'a> {
+    // Bottom crate first
+    stacks: Vec<VecDeque<&'a str>>,
+}
+
+impl<'a> Stacks<'a> {
+    // Reads the drawing of the crates, up to (and including) the row of stack numbers. Crates are
+    // assigned to the stack whose number is written right below them, so the drawing may use
+    // any column widths. Empty slots are either blank or drawn as [-]
+    fn parse(lines: &mut std::str::Lines<'a>) -> Result<Self, String> {
+        let mut rows = Vec::new();
+        let mut labels = None;
+        for line in lines.by_ref() {
+            if line.contains('[') {
+                rows.push(line);
+            } else if !line.trim().is_empty() {
+                labels = Some(line);
+                break;
+            } else {
+                break;
+            }
         }
-        let crates: Vec<&str> = line.split(' ').collect();
-        // Initialize stacks
-        if stacks.len() == 0 {
-            for _ in 0..crates.len() {
-                stacks.push(VecDeque::new());
+        let labels = labels.ok_or("The drawing has no row of stack numbers")?;
+
+        // Column span of each stack number
+        let mut spans = Vec::new();
+        let mut start = None;
+        for (col,c) in labels.char_indices().chain([(labels.len(), ' ')]) {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(col),
+                (true, Some(st)) => {
+                    let label = &labels[st..col];
+                    if label.parse::<usize>().ok() != Some(spans.len() + 1) {
+                        return Err(format!("Expected stack number {}, found {label}", spans.len() + 1));
+                    }
+                    spans.push((st, col - 1));
+                    start = None;
+                },
+                _ => {},
             }
         }
 
-        // Read crates
-        let mut i = 0;
-        for c in crates {
-            let c = c.trim();
-            let id = c.strip_prefix('[').unwrap().strip_suffix(']').unwrap();
-            if id != "-" {
-                stacks[i].push_front(id);
+        let mut stacks = vec![VecDeque::new(); spans.len()];
+        for row in rows {
+            let mut used = vec![false; spans.len()];
+            let mut rest = row;
+            while let Some(open) = rest.find('[') {
+                let close = rest[open..].find(']').ok_or(format!("Unclosed crate in row {row}"))? + open;
+                let id = &rest[open + 1..close];
+                // Columns of the crate within the whole row
+                let (first, last) = (row.len() - rest.len() + open + 1, row.len() - rest.len() + close - 1);
+                let distance = |(a,b): &(usize,usize)| if last < *a {
+                    a - last
+                } else {
+                    first.saturating_sub(*b)
+                };
+                let i = (0..spans.len()).min_by_key(|i| distance(&spans[*i])).ok_or("There are no stacks")?;
+                if used[i] {
+                    return Err(format!("Two crates in row {row} belong to stack {}", i + 1));
+                }
+                used[i] = true;
+                if id != "-" {
+                    stacks[i].push_front(id);
+                }
+                rest = &rest[close + 1..];
             }
-            i += 1;
         }
+        Ok(Self { stacks })
     }
-    // Skip blank line
-    lines.next();
-    
-    // Parse movements
-    while let Some(line) = lines.next() {
-        // "move X from P1 to P2"
-        let mut words = line.split(' ');
-        words.next();
-        let n = words.next().unwrap().trim().parse::<usize>().unwrap();
-        words.next();
-        let p1 = words.next().unwrap().trim().parse::<usize>().unwrap() - 1;
-        words.next();
-        let p2 = words.next().unwrap().trim().parse::<usize>().unwrap() - 1;
-        for _ in 0..n {
-            let id = stacks[p1].pop_back().unwrap();
-            stacks[p2].push_back(id);
+
+    fn stack(&self, i: usize) -> Result<&VecDeque<&'a str>, String> {
+        self.stacks.get(i).ok_or(format!("There is no stack {}", i + 1))
+    }
+
+    fn height(&self, i: usize) -> Result<usize, String> {
+        Ok(self.stack(i)?.len())
+    }
+
+    // Removes n crates from the top (or the bottom) of stack i, returned bottom crate first
+    fn take(&mut self, i: usize, n: usize, from_bottom: bool) -> Result<Vec<&'a str>, String> {
+        let l = self.height(i)?;
+        if l < n {
+            return Err(format!("Cannot take {n} crates from stack {}, which has {l}", i + 1));
         }
+        let stack = &mut self.stacks[i];
+        if from_bottom {
+            Ok(stack.drain(..n).collect())
+        } else {
+            Ok(stack.drain(l - n..).collect())
+        }
+    }
+
+    // Places the crates on top of stack i, in the given order
+    fn put(&mut self, i: usize, crates: impl IntoIterator<Item = &'a str>) -> Result<(), String> {
+        self.stack(i)?;
+        self.stacks[i].extend(crates);
+        Ok(())
     }
 
-    // Combine result
-    let mut res = String::from("");
-    for mut s in stacks {
-        res.push_str(s.pop_back().unwrap());
+    fn tops(&self) -> String {
+        self.stacks.iter().filter_map(|s| s.back().copied()).collect()
     }
-    res
 }
 
-fn run2(input: &str) -> String {
-    let mut stacks = Vec::new();
-    let mut lines = input.lines();
-    while let Some(line) = lines.next() {
-        if line.contains(|c: char| c.is_ascii_digit()) {
-            break;
-        }
-        let crates: Vec<&str> = line.split(' ').collect();
-        // Initialize stacks
-        if stacks.len() == 0 {
-            for _ in 0..crates.len() {
-                stacks.push(VecDeque::new());
-            }
+// Same fixed-width drawing as in the puzzle: blank empty slots and stack numbers centered below
+impl fmt::Display for Stacks<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let widths: Vec<usize> = self.stacks.iter().enumerate()
+            .map(|(i,s)| s.iter().map(|id| id.len() + 2).chain([3, (i + 1).to_string().len()]).max().unwrap())
+            .collect();
+        let height = self.stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let row: Vec<String> = self.stacks.iter().zip(&widths)
+                .map(|(s,w)| match s.get(level) {
+                    Some(id) => format!("{:<w$}", format!("[{id}]")),
+                    None => " ".repeat(*w),
+                })
+                .collect();
+            writeln!(f, "{}", row.join(" "))?;
         }
+        let labels: Vec<String> = widths.iter().enumerate().map(|(i,w)| format!("{:^w$}", i + 1)).collect();
+        write!(f, "{}", labels.join(" "))
+    }
+}
 
-        // Read crates
-        let mut i = 0;
-        for c in crates {
-            let c = c.trim();
-            let id = c.strip_prefix('[').unwrap().strip_suffix(']').unwrap();
-            if id != "-" {
-                stacks[i].push_front(id);
-            }
-            i += 1;
+#[derive(Debug,Clone,Copy,PartialEq)]
+struct Move {
+    n: usize,
+    // Both stacks indexed from 0
+    from: usize,
+    to: usize,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.n, self.from + 1, self.to + 1)
+    }
+}
+
+impl Move {
+    // "move X from P1 to P2"
+    fn parse(line: &str) -> Result<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["move", n, "from", p1, "to", p2] => {
+                let number = |s: &str| s.parse::<usize>().map_err(|_| format!("Invalid number {s} in move {line}"));
+                let (n, p1, p2) = (number(n)?, number(p1)?, number(p2)?);
+                if p1 == 0 || p2 == 0 {
+                    return Err(format!("Stacks are numbered from 1 in move {line}"));
+                }
+                Ok(Move { n, from: p1 - 1, to: p2 - 1 })
+            },
+            _ => Err(format!("Invalid move {line}")),
         }
     }
+}
+
+fn parse(input: &str) -> Result<(Stacks<'_>, Vec<Move>), String> {
+    let mut lines = input.lines();
+    let stacks = Stacks::parse(&mut lines)?;
     // Skip blank line
     lines.next();
-    
-    // Parse movements
-    while let Some(line) = lines.next() {
-        // "move X from P1 to P2"
-        let mut words = line.split(' ');
-        words.next();
-        let n = words.next().unwrap().trim().parse::<usize>().unwrap();
-        words.next();
-        let p1 = words.next().unwrap().trim().parse::<usize>().unwrap() - 1;
-        words.next();
-        let p2 = words.next().unwrap().trim().parse::<usize>().unwrap() - 1;
-        for i in 0..n {
-            let l = stacks[p1].len();
-            let id = stacks[p1].remove(l - (n - i)).unwrap();
-            stacks[p2].push_back(id);
+    let moves = lines.filter(|l| !l.trim().is_empty()).map(Move::parse).collect::<Result<Vec<Move>, String>>()?;
+    Ok((stacks, moves))
+}
+
+trait Crane {
+    // Performs a single move. On error the stacks are left untouched
+    fn apply<'a>(&self, stacks: &mut Stacks<'a>, m: &Move) -> Result<(), String>;
+}
+
+// Moves crates one at a time
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn apply<'a>(&self, stacks: &mut Stacks<'a>, m: &Move) -> Result<(), String> {
+        stacks.height(m.to)?;
+        let crates = stacks.take(m.from, m.n, false)?;
+        stacks.put(m.to, crates.into_iter().rev())
+    }
+}
+
+// Moves several crates at once, keeping their order
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn apply<'a>(&self, stacks: &mut Stacks<'a>, m: &Move) -> Result<(), String> {
+        stacks.height(m.to)?;
+        let crates = stacks.take(m.from, m.n, false)?;
+        stacks.put(m.to, crates)
+    }
+}
+
+// Pulls crates out from the bottom of the stack, keeping their order
+struct BottomLoader;
+
+impl Crane for BottomLoader {
+    fn apply<'a>(&self, stacks: &mut Stacks<'a>, m: &Move) -> Result<(), String> {
+        stacks.height(m.to)?;
+        let crates = stacks.take(m.from, m.n, true)?;
+        stacks.put(m.to, crates)
+    }
+}
+
+// Any crane that can lift at most `capacity` crates in a single go
+struct Limited<C: Crane> {
+    crane: C,
+    capacity: usize,
+}
+
+impl<C: Crane> Crane for Limited<C> {
+    fn apply<'a>(&self, stacks: &mut Stacks<'a>, m: &Move) -> Result<(), String> {
+        if self.capacity == 0 {
+            return Err(String::from("A crane with no capacity cannot move anything"));
+        }
+        stacks.height(m.to)?;
+        let l = stacks.height(m.from)?;
+        if l < m.n {
+            return Err(format!("Cannot take {} crates from stack {}, which has {l}", m.n, m.from + 1));
         }
+        let mut left = m.n;
+        while left > 0 {
+            let n = left.min(self.capacity);
+            self.crane.apply(stacks, &Move { n, ..*m })?;
+            left -= n;
+        }
+        Ok(())
+    }
+}
+
+fn crane(name: &str) -> Option<Box<dyn Crane>> {
+    match name {
+        "9000" => Some(Box::new(CrateMover9000)),
+        "9001" => Some(Box::new(CrateMover9001)),
+        "bottom" => Some(Box::new(BottomLoader)),
+        _ => None,
+    }
+}
+
+// Stacks after the first `steps` moves, or after all of them
+fn stacks_after<'a>(input: &'a str, crane: &dyn Crane, steps: Option<usize>) -> Result<Stacks<'a>, String> {
+    let (mut stacks, moves) = parse(input)?;
+    let steps = steps.unwrap_or(moves.len());
+    for (i,m) in moves.iter().take(steps).enumerate() {
+        crane.apply(&mut stacks, m).map_err(|e| format!("Move {}: {e}", i + 1))?;
     }
+    Ok(stacks)
+}
+
+fn simulate(input: &str, crane: &dyn Crane) -> Result<String, String> {
+    Ok(stacks_after(input, crane, None)?.tops())
+}
 
-    // Combine result
-    let mut res = String::from("");
-    for mut s in stacks {
-        res.push_str(s.pop_back().unwrap());
+// Drawing of the initial stacks, followed by each move and the drawing right after it
+fn replay(input: &str, crane: &dyn Crane) -> Result<String, String> {
+    let (mut stacks, moves) = parse(input)?;
+    let mut out = format!("{stacks}\n");
+    for (i,m) in moves.iter().enumerate() {
+        crane.apply(&mut stacks, m).map_err(|e| format!("Move {}: {e}", i + 1))?;
+        out.push_str(&format!("\n{m}\n\n{stacks}\n"));
     }
-    res
+    Ok(out)
+}
+
+#[cfg(test)]
+fn run1(input: &str) -> String {
+    simulate(input, &CrateMover9000).unwrap_or_else(|e| panic!("{e}"))
+}
+
+#[cfg(test)]
+fn run2(input: &str) -> String {
+    simulate(input, &CrateMover9001).unwrap_or_else(|e| panic!("{e}"))
 }
 
 fn main() {
@@ -122,8 +290,35 @@ fn main() {
 
     let input = fs::read_to_string(filepath).unwrap();
 
-    let res = run2(&input);
-    println!("{res}");
+    let res = match args.next().as_deref() {
+        Some("9000") => simulate(&input, &CrateMover9000),
+        Some("bottom") => simulate(&input, &BottomLoader),
+        Some(mode @ ("render" | "replay")) => {
+            let name = args.next().unwrap_or(String::from("9001"));
+            let Some(crane) = crane(&name) else {
+                eprintln!("I don't know how to drive the crane {name}");
+                process::exit(1);
+            };
+            if mode == "replay" {
+                replay(&input, crane.as_ref())
+            } else {
+                let steps = args.next().and_then(|s| s.parse::<usize>().ok());
+                stacks_after(&input, crane.as_ref(), steps).map(|s| s.to_string())
+            }
+        },
+        Some("limited") => {
+            let capacity = args.next().and_then(|s| s.parse::<usize>().ok()).unwrap_or(1);
+            simulate(&input, &Limited { crane: CrateMover9001, capacity })
+        },
+        _ => simulate(&input, &CrateMover9001),
+    };
+    match res {
+        Ok(res) => println!("{res}"),
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        },
+    }
 }
 
 #[test]
@@ -153,3 +348,53 @@ fn input2() {
     let res = run2(&input);
     assert_eq!(res.as_str(),"FSZWBPTBG");
 }
+
+#[cfg(test)]
+const EXAMPLE: &str = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2
+";
+
+#[test]
+fn render() {
+    let (stacks, _) = parse(EXAMPLE).unwrap();
+    let drawing = stacks.to_string();
+    assert_eq!(drawing, EXAMPLE.split("\n\n").next().unwrap());
+    let mut lines = drawing.lines();
+    assert_eq!(Stacks::parse(&mut lines), Ok(stacks.clone()));
+    let after = stacks_after(EXAMPLE, &CrateMover9000, Some(2)).unwrap();
+    assert_eq!(after.to_string(), "        [Z]\n        [N]\n    [C] [D]\n    [M] [P]\n 1   2   3 ");
+    let steps = replay(EXAMPLE, &CrateMover9001).unwrap();
+    assert!(steps.starts_with(&format!("{stacks}\n\nmove 1 from 2 to 1\n\n[D]        \n")));
+    assert_eq!(steps.matches(" 1   2   3 ").count(), 5);
+}
+
+#[test]
+fn raw_drawing() {
+    let old = "[-] [D] [-]\n[N] [C] [-]\n[Z] [M] [P]\n 1   2   3\n";
+    let (stacks, _) = parse(EXAMPLE).unwrap();
+    assert_eq!(Stacks::parse(&mut old.lines()), Ok(stacks));
+    // Multi-digit stack numbers, and rows not padded up to the last stack
+    let wide = "                                        [K]\n[A]                                 [J] [L]\n 1   2   3   4   5   6   7   8   9  10  11 \n";
+    let stacks = Stacks::parse(&mut wide.lines()).unwrap();
+    assert_eq!(stacks.stacks.len(), 11);
+    assert_eq!(stacks.tops(), "AJK");
+    assert_eq!(Stacks::parse(&mut stacks.to_string().lines()), Ok(stacks));
+    assert!(Stacks::parse(&mut " 1   3 ".lines()).is_err());
+}
+
+#[test]
+fn cranes() {
+    assert_eq!(run1(EXAMPLE), "CMZ");
+    assert_eq!(run2(EXAMPLE), "MCD");
+    assert_eq!(simulate(EXAMPLE, &Limited { crane: CrateMover9001, capacity: 1 }).unwrap(), "CMZ");
+    assert_eq!(simulate(EXAMPLE, &BottomLoader).unwrap(), "DCM");
+    let bad = EXAMPLE.replace("move 2 from 2 to 1", "move 3 from 2 to 1");
+    assert_eq!(simulate(&bad, &CrateMover9000), Err(String::from("Move 3: Cannot take 3 crates from stack 2, which has 2")));
+}
@@ -1,20 +1,140 @@
 use std::{env,fs,process};
-use std::collections::HashSet;
-
-fn run(input: &str, len: u16) -> u16 {
-    let mut i = 0;
-    let mut v = Vec::new();
-    for c in input.chars() {
-        v.push(c);
-        i+= 1;
-        if i >= len {
-            let set: HashSet<char> = HashSet::from_iter(v[((i-len) as usize)..].into_iter().map(|c| *c));
-            if set.len() == len.into() {
-                return i;
+use std::collections::VecDeque;
+use std::io::{self,BufReader,Bytes,Read};
+
+// Sliding window over a stream of bytes, keeping how many times each byte value appears in it
+struct Window {
+    len: usize,
+    bytes: VecDeque<u8>,
+    counts: [usize; 256],
+    // Number of byte values with a non-zero count
+    distinct: usize,
+}
+
+impl Window {
+    fn new(len: usize) -> Self {
+        assert!(len > 0, "A marker must have at least one character");
+        Self { len, bytes: VecDeque::with_capacity(len + 1), counts: [0; 256], distinct: 0 }
+    }
+
+    // Slides the window over the next byte, and tells whether it now holds a marker
+    fn push(&mut self, b: u8) -> bool {
+        self.bytes.push_back(b);
+        self.counts[b as usize] += 1;
+        if self.counts[b as usize] == 1 {
+            self.distinct += 1;
+        }
+        if self.bytes.len() > self.len {
+            let old = self.bytes.pop_front().unwrap() as usize;
+            self.counts[old] -= 1;
+            if self.counts[old] == 0 {
+                self.distinct -= 1;
+            }
+        }
+        self.distinct == self.len
+    }
+
+    fn clear(&mut self) {
+        self.bytes.clear();
+        self.counts = [0; 256];
+        self.distinct = 0;
+    }
+}
+
+// Positions right after every window of `len` different bytes, reading the source only once
+struct Markers<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    window: Window,
+    position: u64,
+}
+
+impl<R: Read> Markers<R> {
+    fn new(reader: R, len: usize) -> Self {
+        Self { bytes: BufReader::new(reader).bytes(), window: Window::new(len), position: 0 }
+    }
+}
+
+impl<R: Read> Iterator for Markers<R> {
+    type Item = io::Result<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for b in self.bytes.by_ref() {
+            let b = match b {
+                Ok(b) => b,
+                Err(e) => return Some(Err(e)),
+            };
+            self.position += 1;
+            if self.window.push(b) {
+                return Some(Ok(self.position));
+            }
+        }
+        None
+    }
+}
+
+fn first_marker<R: Read>(reader: R, len: usize) -> io::Result<Option<u64>> {
+    Markers::new(reader, len).next().transpose()
+}
+
+// First marker for each of the given lengths, all found in a single pass
+fn first_markers<R: Read>(reader: R, lens: &[usize]) -> io::Result<Vec<Option<u64>>> {
+    let mut windows: Vec<Window> = lens.iter().map(|l| Window::new(*l)).collect();
+    let mut found = vec![None; lens.len()];
+    let mut missing = lens.len();
+    for (pos,b) in (1..).zip(BufReader::new(reader).bytes()) {
+        if missing == 0 {
+            break;
+        }
+        let b = b?;
+        for (w,f) in windows.iter_mut().zip(found.iter_mut()) {
+            if w.push(b) && f.is_none() {
+                *f = Some(pos);
+                missing -= 1;
+            }
+        }
+    }
+    Ok(found)
+}
+
+#[derive(Debug,PartialEq)]
+struct Frames {
+    // Position right after the first start-of-packet marker
+    packet: Option<u64>,
+    // Byte ranges [start, end) of the payload of each message. A message starts right after its
+    // start-of-message marker and runs until the next marker begins, or the end of the stream
+    messages: Vec<(u64,u64)>,
+}
+
+// Splits a datastream into messages in a single pass. Markers of consecutive messages never overlap
+fn decode<R: Read>(reader: R, packet_len: usize, message_len: usize) -> io::Result<Frames> {
+    let mut packet = Window::new(packet_len);
+    let mut message = Window::new(message_len);
+    let mut frames = Frames { packet: None, messages: vec![] };
+    let mut position = 0;
+    for b in BufReader::new(reader).bytes() {
+        let b = b?;
+        position += 1;
+        if frames.packet.is_none() && packet.push(b) {
+            frames.packet = Some(position);
+        }
+        if message.push(b) {
+            if let Some(last) = frames.messages.last_mut() {
+                last.1 = position - message_len as u64;
             }
+            frames.messages.push((position, position));
+            message.clear();
         }
     }
-    0
+    if let Some(last) = frames.messages.last_mut() {
+        last.1 = position;
+    }
+    Ok(frames)
+}
+
+// The puzzle examples are given as strings
+#[cfg(test)]
+fn run(input: &str, len: usize) -> Option<u64> {
+    first_marker(input.as_bytes(), len).unwrap()
 }
 
 fn main() {
@@ -29,82 +149,150 @@ fn main() {
         process::exit(1);
     }
 
-    let input = fs::read_to_string(filepath).unwrap();
+    let file = fs::File::open(filepath).unwrap();
+    let check = |len: usize| if len == 0 {
+        eprintln!("A marker must have at least one character!");
+        process::exit(1);
+    };
 
-    let res = run(&input,14);
-    println!("{res}");
+    match args.next().as_deref() {
+        Some("markers") => {
+            let lens: Vec<usize> = args.filter_map(|s| s.parse::<usize>().ok()).collect();
+            lens.iter().for_each(|l| check(*l));
+            for (len,pos) in lens.iter().zip(first_markers(file, &lens).unwrap()) {
+                match pos {
+                    Some(pos) => println!("{len}: {pos}"),
+                    None => println!("{len}: none"),
+                }
+            }
+        },
+        Some("frames") => {
+            let frames = decode(file, 4, 14).unwrap();
+            match frames.packet {
+                Some(pos) => println!("Start of packet: {pos}"),
+                None => println!("Start of packet: none"),
+            }
+            for (i,(start,end)) in frames.messages.iter().enumerate() {
+                println!("Message {}: {start}..{end} ({} bytes)", i + 1, end - start);
+            }
+        },
+        Some("all") => {
+            let len = args.next().and_then(|s| s.parse::<usize>().ok()).unwrap_or(14);
+            check(len);
+            for pos in Markers::new(file, len) {
+                println!("{}", pos.unwrap());
+            }
+        },
+        _ => {
+            match first_marker(file, 14).unwrap() {
+                Some(res) => println!("{res}"),
+                None => {
+                    eprintln!("There is no marker in this datastream");
+                    process::exit(1);
+                },
+            }
+        },
+    }
 }
 
 #[test]
 fn example11() {
     let res = run("mjqjpqmgbljsphdztnvjfqwrcgsmlb",4);
-    assert_eq!(res,7);
+    assert_eq!(res,Some(7));
 }
 
 #[test]
 fn example12() {
     let res = run("bvwbjplbgvbhsrlpgdmjqwftvncz",4);
-    assert_eq!(res,5);
+    assert_eq!(res,Some(5));
 }
 
 #[test]
 fn example13() {
     let res = run("nppdvjthqldpwncqszvftbrmjlhg",4);
-    assert_eq!(res,6);
+    assert_eq!(res,Some(6));
 }
 
 #[test]
 fn example14() {
     let res = run("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",4);
-    assert_eq!(res,10);
+    assert_eq!(res,Some(10));
 }
 
 #[test]
 fn example15() {
     let res = run("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw",4);
-    assert_eq!(res,11);
+    assert_eq!(res,Some(11));
 }
 
 #[test]
 fn input1() {
     let input = fs::read_to_string("input.txt").unwrap();
     let res = run(&input,4);
-    assert_eq!(res,1757);
+    assert_eq!(res,Some(1757));
 }
 
 #[test]
 fn example21() {
     let res = run("mjqjpqmgbljsphdztnvjfqwrcgsmlb",14);
-    assert_eq!(res,19);
+    assert_eq!(res,Some(19));
 }
 
 #[test]
 fn example22() {
     let res = run("bvwbjplbgvbhsrlpgdmjqwftvncz",14);
-    assert_eq!(res,23);
+    assert_eq!(res,Some(23));
 }
 
 #[test]
 fn example23() {
     let res = run("nppdvjthqldpwncqszvftbrmjlhg",14);
-    assert_eq!(res,23);
+    assert_eq!(res,Some(23));
 }
 
 #[test]
 fn example24() {
     let res = run("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",14);
-    assert_eq!(res,29);
+    assert_eq!(res,Some(29));
 }
 
 #[test]
 fn example25() {
     let res = run("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw",14);
-    assert_eq!(res,26);
+    assert_eq!(res,Some(26));
 }
 
 #[test]
 fn input2() {
     let input = fs::read_to_string("input.txt").unwrap();
     let res = run(&input,14);
-    assert_eq!(res,2950);
+    assert_eq!(res,Some(2950));
+}
+
+#[test]
+fn no_marker() {
+    assert_eq!(run("abcabcabc",4), None);
+    assert_eq!(run("",1), None);
+}
+
+#[test]
+fn all_markers() {
+    let markers: Vec<u64> = Markers::new(&b"aabcbdd\xff\x00"[..], 3).map(|p| p.unwrap()).collect();
+    assert_eq!(markers, vec![4,6,9]);
+}
+
+#[test]
+fn several_lengths() {
+    let res = first_markers("mjqjpqmgbljsphdztnvjfqwrcgsmlb".as_bytes(), &[4,14,30]).unwrap();
+    assert_eq!(res, vec![Some(7),Some(19),None]);
+}
+
+#[test]
+fn frames() {
+    let stream = "aabcdaaaaawxyzz";
+    let frames = decode(stream.as_bytes(), 2, 4).unwrap();
+    assert_eq!(frames.packet, Some(3));
+    assert_eq!(frames.messages, vec![(5,9),(13,15)]);
+    assert_eq!(&stream[5..9], "aaaa");
+    assert_eq!(decode("aaaa".as_bytes(), 4, 14).unwrap(), Frames { packet: None, messages: vec![] });
 }
@@ -1,5 +1,5 @@
-use std::{env,fs,process};
-use std::collections::HashMap;
+use std::{env,fmt,fs,process};
+use std::collections::{HashMap,BTreeSet};
 use std::cell::RefCell;
 
 #[derive(Debug)]
@@ -52,6 +52,7 @@ impl<'a> FSTree<'a> {
                     self.max_id += 1;
                     v.push((self.max_id,filename));
                     self.tree.insert(self.max_id, file);
+                    self.invalidate(dirid);
                 },
                 _ => {
                     panic!("Node {dirid} is not a directory");
@@ -62,6 +63,17 @@ impl<'a> FSTree<'a> {
         }
     }
 
+    // Forgets the cached sizes of a directory and all its ancestors
+    fn invalidate(&self, dirid: &u32) {
+        let mut current = Some(*dirid);
+        while let Some(id) = current {
+            if let Some(FSNode::Directory(_,cell,_)) = self.tree.get(&id) {
+                cell.replace(None);
+            }
+            current = self.parent(&id);
+        }
+    }
+
     fn size(&self, id: &u32) -> u64 {
         if let Some(node) = self.tree.get(id) {
             match node {
@@ -84,123 +96,494 @@ impl<'a> FSTree<'a> {
             panic!("Node {id} does not exist");
         }
     }
+
+    fn parent(&self, id: &u32) -> Option<u32> {
+        match self.tree.get(id)? {
+            FSNode::File(_,parent) => Some(*parent),
+            FSNode::Directory(_,_,parent) => *parent,
+        }
+    }
+
+    fn is_dir(&self, id: &u32) -> bool {
+        matches!(self.tree.get(id), Some(FSNode::Directory(_,_,_)))
+    }
+
+    // Children of a directory, in the order they were created. Files have no children
+    fn list(&self, id: &u32) -> &[(u32,&'a str)] {
+        match self.tree.get(id) {
+            Some(FSNode::Directory(v,_,_)) => v,
+            _ => &[],
+        }
+    }
+
+    fn child(&self, dirid: &u32, name: &str) -> Option<u32> {
+        self.list(dirid).iter().find(|(_,n)| *n == name).map(|(n,_)| *n)
+    }
+
+    fn name(&self, id: &u32) -> &'a str {
+        match self.parent(id) {
+            Some(parent) => self.list(&parent).iter().find(|(n,_)| n == id).unwrap().1,
+            None => "/",
+        }
+    }
+
+    // Absolute path of a node, like /a/e/i
+    fn path(&self, id: &u32) -> String {
+        let mut names = Vec::new();
+        let mut current = *id;
+        while let Some(parent) = self.parent(&current) {
+            names.push(self.name(&current));
+            current = parent;
+        }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    // Node reached following the given path from a directory. Absolute paths start at the root
+    fn resolve_from(&self, dirid: &u32, path: &str) -> Option<u32> {
+        let mut current = if path.starts_with('/') { 0 } else { *dirid };
+        for name in path.split('/') {
+            current = match name {
+                "" | "." => current,
+                ".." => self.parent(&current).unwrap_or(current),
+                s => self.child(&current, s)?,
+            };
+        }
+        Some(current)
+    }
+
+    fn resolve(&self, path: &str) -> Option<u32> {
+        self.resolve_from(&0, path)
+    }
+
+    // Every node under id (id included), each directory before its contents
+    fn walk(&self, id: &u32) -> Walk<'_, 'a> {
+        Walk { tree: self, stack: vec![*id] }
+    }
+
+    fn entry(&self, id: &u32) -> Entry<'a> {
+        Entry { id: *id, name: self.name(id), size: self.size(id), is_dir: self.is_dir(id) }
+    }
+
+    // Nodes under id satisfying the predicate
+    fn find(&self, id: &u32, predicate: impl Fn(&Entry) -> bool) -> Vec<Entry<'a>> {
+        self.walk(id).map(|n| self.entry(&n)).filter(|e| predicate(e)).collect()
+    }
+
+    // Size and path of every directory under id, contents before the directory itself, like du
+    fn du(&self, id: &u32) -> String {
+        let mut out = String::new();
+        for (n,_) in self.list(id) {
+            if self.is_dir(n) {
+                out.push_str(&self.du(n));
+            }
+        }
+        out.push_str(&format!("{}\t{}\n", self.size(id), self.path(id)));
+        out
+    }
+}
+
+// Text view in the style of the tree command, with the size of every node
+fn tree_view(tree: &FSTree, id: &u32) -> String {
+    fn children(tree: &FSTree, id: &u32, prefix: &str, out: &mut String) {
+        let list = tree.list(id);
+        for (i,(n,name)) in list.iter().enumerate() {
+            let last = i + 1 == list.len();
+            let branch = if last { "└── " } else { "├── " };
+            out.push_str(&format!("{prefix}{branch}{name} ({})\n", tree.size(n)));
+            let indent = if last { "    " } else { "│   " };
+            children(tree, n, &format!("{prefix}{indent}"), out);
+        }
+    }
+    let mut out = format!("{} ({})\n", tree.path(id), tree.size(id));
+    children(tree, id, "", &mut out);
+    out
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn to_json(tree: &FSTree, id: &u32) -> String {
+    let name = json_string(tree.name(id));
+    let size = tree.size(id);
+    if tree.is_dir(id) {
+        let children: Vec<String> = tree.list(id).iter().map(|(n,_)| to_json(tree, n)).collect();
+        format!("{{\"name\":{name},\"type\":\"dir\",\"size\":{size},\"children\":[{}]}}", children.join(","))
+    } else {
+        format!("{{\"name\":{name},\"type\":\"file\",\"size\":{size}}}")
+    }
 }
 
-fn read_log(input: &str) -> FSTree {
+// Reads a tree in the format of the puzzle statement, two spaces of indentation per level:
+// - / (dir)
+//   - a (dir)
+//     - i (file, size=584)
+fn read_description(input: &str) -> Result<FSTree<'_>, String> {
     let mut tree = FSTree::new();
-    let mut lines = input.lines();
-    lines.next();
-    let mut line = lines.next().unwrap();
-    let mut current_dir = 0;
-    loop {
-        if let Some(dirname) = line.strip_prefix("$ cd") {
-            let dir = tree.tree.get(&current_dir).unwrap();
-            current_dir = match dirname.trim() {
-                ".." => {
-                    match *dir {
-                        FSNode::Directory(_,_,Some(parent)) => parent,
-                        _ => {
-                            panic!("Directory {current_dir} has no parent");
-                        },
-                    }
-                },
-                s => {
-                    match dir {
-                        FSNode::Directory(v,_,_) => {
-                            let mut id = 0;
-                            let mut found = false;
-                            for (n,name) in v {
-                                if *name == s {
-                                    id = *n;
-                                    found = true;
-                                    break;
-                                }
-                            }
-                            if found {
-                                id
-                            } else {
-                                panic!("Directory {s} is unreachable from {current_dir}");
-                            }
-                        },
-                        _ => panic!("Node {current_dir} is not a directory"),
-                    }
-                },
-            };
-            if let Some(s) = lines.next() {
-                line = s;
+    // Directory at each depth of the current branch
+    let mut branch: Vec<u32> = Vec::new();
+    for line in input.lines().filter(|l| !l.trim().is_empty()) {
+        let content = line.trim_start();
+        let indent = line.len() - content.len();
+        if indent % 2 != 0 {
+            return Err(format!("Line {line} is not indented by two spaces per level"));
+        }
+        let depth = indent / 2;
+        // Names may have parentheses of their own, so the kind is in the last pair
+        let (name, kind) = content.strip_prefix("- ")
+            .and_then(|c| c.strip_suffix(')'))
+            .and_then(|c| c.rsplit_once(" ("))
+            .ok_or(format!("Invalid line {line}"))?;
+        if depth == 0 {
+            if name != "/" || kind != "dir" || !branch.is_empty() {
+                return Err(format!("The tree must start with a single root directory, found {line}"));
+            }
+            branch.push(0);
+            continue;
+        }
+        if depth > branch.len() {
+            return Err(format!("Line {line} is indented too deep"));
+        }
+        branch.truncate(depth);
+        let parent = branch[depth - 1];
+        if tree.child(&parent, name).is_some() {
+            return Err(format!("{name} appears twice in {}", tree.path(&parent)));
+        }
+        if kind == "dir" {
+            branch.push(tree.new_dir(&parent, name));
+        } else {
+            let size = kind.strip_prefix("file, size=")
+                .and_then(|s| s.parse::<u64>().ok())
+                .ok_or(format!("Invalid line {line}"))?;
+            tree.new_file(&parent, name, size);
+        }
+    }
+    Ok(tree)
+}
+
+// Terminal session that explores the whole tree, listing every directory once
+fn to_session(tree: &FSTree) -> String {
+    fn visit(tree: &FSTree, id: &u32, out: &mut String) {
+        out.push_str("$ ls\n");
+        for (n,name) in tree.list(id) {
+            if tree.is_dir(n) {
+                out.push_str(&format!("dir {name}\n"));
             } else {
-                break;
+                out.push_str(&format!("{} {name}\n", tree.size(n)));
             }
-        } else if line.trim() == "$ ls" {
-            let mut op = lines.next();
-            while let Some(l) = op {
-                if l.starts_with('$') {
-                    break;
+        }
+        for (n,name) in tree.list(id) {
+            if tree.is_dir(n) {
+                out.push_str(&format!("$ cd {name}\n"));
+                visit(tree, n, out);
+                out.push_str("$ cd ..\n");
+            }
+        }
+    }
+    let mut out = String::from("$ cd /\n");
+    visit(tree, &0, &mut out);
+    while let Some(s) = out.strip_suffix("$ cd ..\n") {
+        out.truncate(s.len());
+    }
+    out
+}
+
+#[derive(Debug,Clone,PartialEq)]
+struct Entry<'a> {
+    id: u32,
+    name: &'a str,
+    size: u64,
+    is_dir: bool,
+}
+
+struct Walk<'t, 'a> {
+    tree: &'t FSTree<'a>,
+    stack: Vec<u32>,
+}
+
+impl Iterator for Walk<'_, '_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let id = self.stack.pop()?;
+        self.stack.extend(self.tree.list(&id).iter().rev().map(|(n,_)| *n));
+        Some(id)
+    }
+}
+
+#[derive(Debug,PartialEq)]
+enum Conflict {
+    // Path seen both as a file and as a directory
+    Kind(String),
+    // File listed with two different sizes: path, first and later size
+    Size(String,u64,u64),
+    // Path present in one listing of its directory but not in another
+    Missing(String),
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::Kind(path) => write!(f, "{path} is both a file and a directory"),
+            Conflict::Size(path,a,b) => write!(f, "{path} was listed with sizes {a} and {b}"),
+            Conflict::Missing(path) => write!(f, "{path} is missing from some listing of its directory"),
+        }
+    }
+}
+
+// Directory reached from dirid through the given path, creating the directories that have not
+// been seen yet. Stops with the path of the first component that is a file
+fn enter<'a>(tree: &mut FSTree<'a>, dirid: u32, path: &'a str) -> Result<u32, String> {
+    let mut current = if path.starts_with('/') { 0 } else { dirid };
+    for name in path.split('/') {
+        current = match name {
+            "" | "." => current,
+            ".." => tree.parent(&current).unwrap_or(current),
+            s => match tree.child(&current, s) {
+                Some(n) if tree.is_dir(&n) => n,
+                Some(n) => return Err(tree.path(&n)),
+                None => tree.new_dir(&current, s),
+            },
+        };
+    }
+    Ok(current)
+}
+
+// Rebuilds the file system from a terminal session, together with every inconsistency found.
+// The session may start anywhere, list a directory several times and run unknown commands,
+// whose output is ignored
+fn read_session(input: &str) -> (FSTree<'_>, Vec<Conflict>) {
+    let mut tree = FSTree::new();
+    let mut conflicts = Vec::new();
+    // Names in the first listing of each directory
+    let mut listings: HashMap<u32, Vec<&str>> = HashMap::new();
+    let mut current_dir = 0;
+    let mut lines = input.lines().peekable();
+    while let Some(line) = lines.next() {
+        // Output lines out of a listing are skipped
+        let Some(command) = line.strip_prefix('$') else {
+            continue;
+        };
+        let mut words = command.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("cd"), Some(path), None) => {
+                match enter(&mut tree, current_dir, path) {
+                    Ok(n) => current_dir = n,
+                    Err(file) => conflicts.push(Conflict::Kind(file)),
                 }
-                if let Some(dirname) = l.strip_prefix("dir ") {
-                    let dirname = dirname.trim();
-                    tree.new_dir(&current_dir,dirname);
-                } else {
-                    let vector: Vec<&str> = l.split(' ').collect();
-                    let size = vector[0].parse::<u64>().unwrap();
-                    tree.new_file(&current_dir, vector[1],size);
+            },
+            (Some("ls"), None, None) => {
+                let mut names = Vec::new();
+                while let Some(l) = lines.next_if(|l| !l.starts_with('$')) {
+                    let mut parts = l.split_whitespace();
+                    let (Some(kind), Some(name), None) = (parts.next(), parts.next(), parts.next()) else {
+                        continue;
+                    };
+                    let existing = tree.child(&current_dir, name);
+                    if kind == "dir" {
+                        match existing {
+                            Some(n) if !tree.is_dir(&n) => conflicts.push(Conflict::Kind(tree.path(&n))),
+                            Some(_) => {},
+                            None => { tree.new_dir(&current_dir, name); },
+                        }
+                    } else if let Ok(size) = kind.parse::<u64>() {
+                        match existing {
+                            Some(n) if tree.is_dir(&n) => conflicts.push(Conflict::Kind(tree.path(&n))),
+                            Some(n) if tree.size(&n) != size => {
+                                conflicts.push(Conflict::Size(tree.path(&n), tree.size(&n), size));
+                            },
+                            Some(_) => {},
+                            None => tree.new_file(&current_dir, name, size),
+                        }
+                    } else {
+                        continue;
+                    }
+                    names.push(name);
                 }
-                op = lines.next();
-            }
-            if let Some(s) = op {
-                line = s;
-            } else {
-                break;
-            }
+                names.sort();
+                names.dedup();
+                match listings.get(&current_dir) {
+                    Some(first) => {
+                        for name in first.iter().filter(|n| !names.contains(n)).chain(names.iter().filter(|n| !first.contains(n))) {
+                            let n = tree.child(&current_dir, name).unwrap();
+                            conflicts.push(Conflict::Missing(tree.path(&n)));
+                        }
+                    },
+                    None => { listings.insert(current_dir, names); },
+                }
+            },
+            _ => {},
         }
     }
-    tree
+    (tree, conflicts)
+}
+
+fn read_log(input: &str) -> FSTree<'_> {
+    read_session(input).0
 }
 
 fn run1(input: &str) -> u64 {
     let fstree = read_log(input);
     let max_size = 100000;
-    let mut sum = 0;
-    for (id,node) in &fstree.tree {
-        match node {
-            FSNode::Directory(_,_,_) => {
-                let s = fstree.size(id);
-                if s < max_size {
-                    sum += s;
-                }
-            },
-            _ => {},
+    fstree.find(&0, |e| e.is_dir && e.size < max_size).iter().map(|e| e.size).sum()
+}
+
+#[derive(Debug,PartialEq)]
+struct Plan {
+    // Space that has to be freed
+    needed: u64,
+    // Smallest directory freeing enough space on its own, with its size
+    single: Option<(String,u64)>,
+    // Directories, none inside another, freeing enough space while deleting as little as possible,
+    // with their total size
+    set: Option<(Vec<String>,u64)>,
+}
+
+// Set of totals below a limit. Only the totals that can actually be reached are kept, so disk
+// sizes don't matter, only how many different sums of directory sizes there are
+#[derive(Clone)]
+struct Totals {
+    set: BTreeSet<u64>,
+    limit: u64,
+}
+
+impl Totals {
+    fn new(limit: u64) -> Self {
+        Self { set: BTreeSet::new(), limit }
+    }
+
+    fn insert(&mut self, t: u64) {
+        if t < self.limit {
+            self.set.insert(t);
         }
     }
-    sum
+
+    fn contains(&self, t: u64) -> bool {
+        self.set.contains(&t)
+    }
+
+    fn union(&mut self, other: &Self) {
+        self.set.extend(&other.set);
+    }
+
+    // Adds every total of other increased by d, except those reaching the limit
+    fn union_shifted(&mut self, other: &Self, d: u64) {
+        for t in other.set.range(..self.limit.saturating_sub(d)) {
+            self.set.insert(t + d);
+        }
+    }
+
+    // Smallest total in the set not below t
+    fn first_from(&self, t: u64) -> Option<u64> {
+        self.set.range(t..).next().copied()
+    }
+}
+
+// Directories are decided in preorder: keeping one moves on to the next position, its first
+// subdirectory, while deleting it jumps to the position right after its subtree. Calls visit with
+// each position and the totals below the limit that can be freed before deciding on it
+fn preorder_totals(sizes: &[u64], ends: &[usize], limit: u64, mut visit: impl FnMut(usize, &Totals)) {
+    // Totals reaching each position by deleting some earlier directory
+    let mut pending: HashMap<usize, Totals> = HashMap::new();
+    let mut totals = Totals::new(limit);
+    totals.insert(0);
+    for i in 0..sizes.len() {
+        if let Some(t) = pending.remove(&i) {
+            totals.union(&t);
+        }
+        visit(i, &totals);
+        let deleted = pending.entry(ends[i]).or_insert_with(|| Totals::new(limit));
+        deleted.union_shifted(&totals, sizes[i]);
+    }
+}
+
+// Non-nested directories freeing at least `needed` while deleting as little as possible, with
+// their total size. Memory and time grow with the number of directories times the number of
+// different totals below needed that some of them add up to
+fn cheapest_deletion(tree: &FSTree, needed: u64) -> Option<(u64, Vec<u32>)> {
+    let dirs: Vec<u32> = tree.walk(&0).filter(|n| tree.is_dir(n)).collect();
+    let limit = needed;
+    let sizes: Vec<u64> = dirs.iter().map(|n| tree.size(n)).collect();
+    let ends: Vec<usize> = dirs.iter().enumerate()
+        .map(|(i,n)| i + tree.walk(n).filter(|m| tree.is_dir(m)).count())
+        .collect();
+
+    // The last directory deleted, the total freed before it and the final total
+    let mut best: Option<(usize, u64, u64)> = None;
+    preorder_totals(&sizes, &ends, limit, |i, totals| {
+        if let Some(before) = totals.first_from(limit.saturating_sub(sizes[i])) {
+            let total = before + sizes[i];
+            if best.is_none_or(|(_,_,t)| total < t) {
+                best = Some((i, before, total));
+            }
+        }
+    });
+    let (last, mut before, total) = best?;
+
+    // Walk back through the deleted directories: any earlier one whose subtree ends before the
+    // current position and that completes the remaining total will do
+    let mut chosen = vec![dirs[last]];
+    let mut position = last;
+    while before > 0 {
+        let mut previous = None;
+        preorder_totals(&sizes[..position], &ends[..position], limit, |k, totals| {
+            if previous.is_none() && ends[k] <= position && sizes[k] <= before && totals.contains(before - sizes[k]) {
+                previous = Some(k);
+            }
+        });
+        let k = previous.unwrap();
+        chosen.push(dirs[k]);
+        before -= sizes[k];
+        position = k;
+    }
+    chosen.reverse();
+    Some((total, chosen))
+}
+
+// Space to free so that at least `required` is free on a disk of the given capacity
+fn space_needed(tree: &FSTree, capacity: u64, required: u64) -> u64 {
+    (required + tree.size(&0)).saturating_sub(capacity)
+}
+
+// Smallest directory freeing at least `needed` on its own
+fn smallest_deletion<'a>(tree: &FSTree<'a>, needed: u64) -> Option<Entry<'a>> {
+    tree.find(&0, |e| e.is_dir && e.size >= needed).into_iter().min_by_key(|e| e.size)
+}
+
+// What to delete so that at least `required` space is free on a disk of the given capacity
+fn plan_deletion(tree: &FSTree, capacity: u64, required: u64) -> Plan {
+    let needed = space_needed(tree, capacity, required);
+    if needed == 0 {
+        return Plan { needed, single: None, set: Some((vec![], 0)) };
+    }
+    let single = smallest_deletion(tree, needed).map(|e| (tree.path(&e.id), e.size));
+    if single.is_none() {
+        // Not even deleting everything is enough
+        return Plan { needed, single, set: None };
+    }
+    let set = cheapest_deletion(tree, needed)
+        .map(|(s,ids)| (ids.iter().map(|n| tree.path(n)).collect(), s));
+    Plan { needed, single, set }
 }
 
 fn run2(input: &str) -> u64 {
     let fstree = read_log(input);
-    let total = 70_000_000;
-    let required = 30_000_000;
-    let used = fstree.size(&0);
-    //println!("{used}");
-    if total >= required + used {
+    let needed = space_needed(&fstree, 70_000_000, 30_000_000);
+    if needed == 0 {
         return 0;
-    } else {
-        let bound = required + used - total;
-        //println!("{bound}");
-        let mut min = 0;
-        for (id,node) in &fstree.tree {
-            match node {
-                FSNode::Directory(_,_,_) => {
-                    let s = fstree.size(id);
-                    //println!("{id}: {s}");
-                    if s >= bound && (min == 0 || s < min) {
-                        min = s;
-                    }
-                },
-                _ => {},
-            }
-        }
-        return min;
     }
+    smallest_deletion(&fstree, needed).map(|e| e.size).unwrap_or(0)
 }
 
 fn main() {
@@ -217,8 +600,85 @@ fn main() {
 
     let input = fs::read_to_string(filepath).unwrap();
 
-    let res = run2(&input);
-    println!("{res}");
+    match args.next().as_deref() {
+        Some("plan") => {
+            let fstree = read_log(&input);
+            let capacity = args.next().and_then(|s| s.parse::<u64>().ok()).unwrap_or(70_000_000);
+            let required = args.next().and_then(|s| s.parse::<u64>().ok()).unwrap_or(30_000_000);
+            let plan = plan_deletion(&fstree, capacity, required);
+            println!("Space to free: {}", plan.needed);
+            match plan.single {
+                Some((path,size)) => println!("Smallest directory: {path} ({size})"),
+                None => println!("Smallest directory: none"),
+            }
+            match plan.set {
+                Some((paths,size)) => println!("Smallest set: {} ({size})", paths.join(" ")),
+                None => println!("Smallest set: none"),
+            }
+        },
+        Some("tree") => print!("{}", tree_view(&read_log(&input), &0)),
+        Some("json") => println!("{}", to_json(&read_log(&input), &0)),
+        Some("session") => {
+            // The input is a tree description instead of a terminal session
+            match read_description(&input) {
+                Ok(tree) => print!("{}", to_session(&tree)),
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                },
+            }
+        },
+        Some("conflicts") => {
+            let (_, conflicts) = read_session(&input);
+            for c in conflicts {
+                println!("{c}");
+            }
+        },
+        Some("small") => println!("{}", run1(&input)),
+        Some("du") => {
+            let fstree = read_log(&input);
+            let path = args.next().unwrap_or(String::from("/"));
+            match fstree.resolve(&path) {
+                Some(id) => print!("{}", fstree.du(&id)),
+                None => {
+                    eprintln!("No such file or directory: {path}");
+                    process::exit(1);
+                },
+            }
+        },
+        Some("ls") => {
+            let fstree = read_log(&input);
+            let path = args.next().unwrap_or(String::from("/"));
+            match fstree.resolve(&path) {
+                Some(id) => {
+                    for (n,name) in fstree.list(&id) {
+                        if fstree.is_dir(n) {
+                            println!("dir {name}");
+                        } else {
+                            println!("{} {name}", fstree.size(n));
+                        }
+                    }
+                },
+                None => {
+                    eprintln!("No such file or directory: {path}");
+                    process::exit(1);
+                },
+            }
+        },
+        Some("find") => {
+            // Files or directories whose name contains the pattern and with at least the given size
+            let fstree = read_log(&input);
+            let pattern = args.next().unwrap_or_default();
+            let min_size = args.next().and_then(|s| s.parse::<u64>().ok()).unwrap_or(0);
+            for e in fstree.find(&0, |e| e.name.contains(&pattern) && e.size >= min_size) {
+                println!("{}\t{}", e.size, fstree.path(&e.id));
+            }
+        },
+        _ => {
+            let res = run2(&input);
+            println!("{res}");
+        },
+    }
 }
 
 #[test]
@@ -248,3 +708,210 @@ fn input2() {
     let res = run2(&input);
     assert_eq!(res,3866390);
 }
+
+#[cfg(test)]
+const EXAMPLE: &str = "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k
+";
+
+#[test]
+fn paths() {
+    let fstree = read_log(EXAMPLE);
+    let i = fstree.resolve("/a/e/i").unwrap();
+    assert_eq!(fstree.path(&i), "/a/e/i");
+    assert_eq!(fstree.size(&i), 584);
+    assert_eq!(fstree.resolve_from(&i, "../../../d/j"), fstree.resolve("/d/j"));
+    assert!(fstree.resolve("/d/j").is_some());
+    assert_eq!(fstree.resolve("/a/x"), None);
+    let names: Vec<&str> = fstree.list(&0).iter().map(|(_,n)| *n).collect();
+    assert_eq!(names, vec!["a","b.txt","c.dat","d"]);
+    let walk: Vec<String> = fstree.walk(&fstree.resolve("/a").unwrap()).map(|n| fstree.path(&n)).collect();
+    assert_eq!(walk, vec!["/a","/a/e","/a/e/i","/a/f","/a/g","/a/h.lst"]);
+    let found: Vec<&str> = fstree.find(&0, |e| !e.is_dir && e.name.starts_with('d')).iter().map(|e| e.name).collect();
+    assert_eq!(found, vec!["d.log","d.ext"]);
+    assert_eq!(fstree.du(&0), "584\t/a/e\n94853\t/a\n24933642\t/d\n48381165\t/\n");
+    assert_eq!(run1(EXAMPLE), 95437);
+    assert_eq!(run2(EXAMPLE), 24933642);
+}
+
+#[test]
+fn tolerant_session() {
+    let input = "$ cd a
+$ ls
+10 x
+dir b
+$ pwd
+/a
+$ cd /a/b/c
+$ ls
+5 y
+$ cd /
+$ ls
+dir a
+7 z
+$ cd a
+$ ls
+10 x
+20 x
+dir b
+oops
+dir w
+$ cd x
+";
+    let (fstree, conflicts) = read_session(input);
+    assert_eq!(fstree.size(&0), 22);
+    assert_eq!(fstree.size(&fstree.resolve("/a/b/c/y").unwrap()), 5);
+    assert_eq!(fstree.list(&fstree.resolve("/a").unwrap()).len(), 3);
+    assert_eq!(conflicts, vec![
+        Conflict::Size(String::from("/a/x"), 10, 20),
+        Conflict::Missing(String::from("/a/w")),
+        Conflict::Kind(String::from("/a/x")),
+    ]);
+    // Reading the same log twice does not duplicate anything
+    let twice = format!("{EXAMPLE}$ cd /\n{EXAMPLE}");
+    let (fstree, conflicts) = read_session(&twice);
+    assert_eq!(fstree.size(&0), 48381165);
+    assert!(conflicts.is_empty());
+}
+
+#[test]
+fn export() {
+    let fstree = read_log(EXAMPLE);
+    let view = tree_view(&fstree, &0);
+    assert!(view.starts_with("/ (48381165)\n├── a (94853)\n│   ├── e (584)\n│   │   └── i (584)\n│   ├── f (29116)\n"));
+    assert!(view.ends_with("└── d (24933642)\n    ├── j (4060174)\n    ├── d.log (8033020)\n    ├── d.ext (5626152)\n    └── k (7214296)\n"));
+    let json = to_json(&fstree, &fstree.resolve("/a/e").unwrap());
+    assert_eq!(json, "{\"name\":\"e\",\"type\":\"dir\",\"size\":584,\"children\":[{\"name\":\"i\",\"type\":\"file\",\"size\":584}]}");
+    assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+}
+
+#[test]
+fn description() {
+    let description = "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - j (file, size=4060174)
+    - d.log (file, size=8033020)
+    - d.ext (file, size=5626152)
+    - k (file, size=7214296)
+";
+    let tree = read_description(description).unwrap();
+    let session = to_session(&tree);
+    assert_eq!(tree_view(&read_log(&session), &0), tree_view(&tree, &0));
+    assert_eq!(run1(&session), 95437);
+    assert!(read_description("- / (dir)\n    - a (dir)\n").is_err());
+    assert!(read_description("- / (dir)\n   - a (dir)\n").is_err());
+    let copy = read_description("- / (dir)\n  - a (copy) (dir)\n    - b (2) (file, size=10)\n").unwrap();
+    assert_eq!(copy.resolve("/a (copy)/b (2)").map(|n| copy.size(&n)), Some(10));
+}
+
+#[test]
+fn planner() {
+    let fstree = read_log(EXAMPLE);
+    let plan = plan_deletion(&fstree, 70_000_000, 30_000_000);
+    assert_eq!(plan.needed, 8381165);
+    assert_eq!(plan.single, Some((String::from("/d"), 24933642)));
+    assert_eq!(plan.set, Some((vec![String::from("/d")], 24933642)));
+    // Deleting /a and /a/e together is not allowed, so /a alone is the best set
+    let plan = plan_deletion(&fstree, 48381165, 90000);
+    assert_eq!(plan.single, Some((String::from("/a"), 94853)));
+    assert_eq!(plan.set, Some((vec![String::from("/a")], 94853)));
+    assert_eq!(plan_deletion(&fstree, 100_000_000, 1000).set, Some((vec![], 0)));
+    let everything = Plan { needed: 48381165, single: Some((String::from("/"), 48381165)), set: Some((vec![String::from("/")], 48381165)) };
+    assert_eq!(plan_deletion(&fstree, 48381165, 48381165), everything);
+    assert_eq!(plan_deletion(&fstree, 48381165, 48381166).single, None);
+}
+
+#[test]
+fn planner_set() {
+    let session = "$ cd /
+$ ls
+dir a
+dir b
+dir c
+$ cd a
+$ ls
+60 x
+$ cd ..
+$ cd b
+$ ls
+50 y
+$ cd ..
+$ cd c
+$ ls
+100 z
+dir d
+$ cd d
+$ ls
+5 w
+";
+    let fstree = read_log(session);
+    let plan = plan_deletion(&fstree, 300, 100);
+    assert_eq!(plan.needed, 15);
+    assert_eq!(plan.single, Some((String::from("/b"), 50)));
+    let plan = plan_deletion(&fstree, 300, 195);
+    assert_eq!(plan.needed, 110);
+    assert_eq!(plan.single, Some((String::from("/"), 215)));
+    assert_eq!(plan.set, Some((vec![String::from("/a"), String::from("/b")], 110)));
+}
+
+#[test]
+fn planner_big_disk() {
+    assert_eq!(run2("$ cd /\n$ ls\n500000000000 big\n"), 500000000000);
+    // Nothing to delete when the tree already fits
+    let small = "$ cd /\n$ ls\ndir a\n10 x\n$ cd a\n$ ls\n5 y\n";
+    assert_eq!(run2(small), 0);
+    assert_eq!(plan_deletion(&read_log(small), 70_000_000, 30_000_000).single, None);
+    let session = "$ cd /
+$ ls
+dir a
+dir b
+dir c
+dir d
+$ cd a
+$ ls
+150000000000 x
+$ cd ../b
+$ ls
+120000000000 y
+$ cd ../c
+$ ls
+90000000000 z
+$ cd ../d
+$ ls
+70000000000 w
+";
+    let fstree = read_log(session);
+    let plan = plan_deletion(&fstree, 500_000_000_000, 270_000_000_000);
+    assert_eq!(plan.needed, 200_000_000_000);
+    assert_eq!(plan.single, Some((String::from("/"), 430_000_000_000)));
+    assert_eq!(plan.set, Some((vec![String::from("/b"), String::from("/c")], 210_000_000_000)));
+}
@@ -1,149 +1,183 @@
-use std::{env,fs,process};
-use std::collections::HashSet;
+use std::{env,fmt,fs,io,process};
+use std::path::Path;
 
-fn run1(input: &str) -> usize {
-    let mut heights = Vec::new();
-    // Read input
-    for line in input.lines() {
-        let mut row = Vec::new();
-        for c in line.chars() {
-            row.push(c.to_digit(10).unwrap());
-        }
-        heights.push(row);
-    }
-    // Get all visible trees
-    let mut visible = HashSet::with_capacity(heights.len() * heights[0].len());
-    //From left and right
-    for i in 0..heights.len() {
-        let mut j = 1;
-        visible.insert((i,0));
-        let mut maxh = heights[i][0];
-        while j < heights[i].len() {
-            if maxh < heights[i][j] {
-                visible.insert((i,j));
-                maxh = heights[i][j];
-            }
-            j += 1;
-        }
-        j = heights[i].len() - 1;
-        maxh = heights[i][j];
-        visible.insert((i, j));
-        loop {
-            j -= 1;
-            if maxh < heights[i][j] {
-                visible.insert((i,j));
-                maxh = heights[i][j];
-            }
-            if j == 0 {
-                break;
-            }
+// Rectangular grid, stored row by row
+#[derive(Debug,Clone,PartialEq)]
+struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Copy> Grid<T> {
+    fn new(width: usize, height: usize, value: T) -> Self {
+        Self { width, height, cells: vec![value; width * height] }
+    }
+
+    fn get(&self, i: usize, j: usize) -> T {
+        self.cells[i * self.width + j]
+    }
+
+    fn set(&mut self, i: usize, j: usize, value: T) {
+        self.cells[i * self.width + j] = value;
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[T]> {
+        // An empty grid has no rows, whatever its width
+        self.cells.chunks(self.width.max(1))
+    }
+
+    fn map<U>(&self, f: impl Fn(T) -> U) -> Grid<U> {
+        Grid { width: self.width, height: self.height, cells: self.cells.iter().map(|v| f(*v)).collect() }
+    }
+}
+
+impl<T: Copy + fmt::Display> Grid<T> {
+    fn to_csv(&self) -> String {
+        let mut out = String::new();
+        for row in self.rows() {
+            let values: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            out.push_str(&values.join(","));
+            out.push('\n');
         }
+        out
     }
-    // From up and down
-    for j in 0..heights[0].len() {
-        let mut i = 1;
-        visible.insert((0,j));
-        let mut maxh = heights[0][j];
-        while i < heights.len() {
-            if maxh < heights[i][j] {
-                visible.insert((i,j));
-                maxh = heights[i][j];
-            }
-            i += 1;
+}
+
+impl<T: Copy> Grid<T> {
+    // Plain (ASCII) grayscale image, one pixel per cell
+    fn to_pgm(&self, gray: impl Fn(T) -> u8) -> String {
+        let mut out = format!("P2\n{} {}\n255\n", self.width, self.height);
+        for row in self.rows() {
+            let values: Vec<String> = row.iter().map(|v| gray(*v).to_string()).collect();
+            out.push_str(&values.join(" "));
+            out.push('\n');
         }
-        i = heights.len() - 1;
-        visible.insert((i, j));
-        maxh = heights[i][j];
-        loop {
-            i -= 1;
-            if maxh < heights[i][j] {
-                visible.insert((i,j));
-                maxh = heights[i][j];
-            }
-            if i == 0 {
-                break;
-            }
+        out
+    }
+
+    // Plain (ASCII) color image, one pixel per cell
+    fn to_ppm(&self, color: impl Fn(T) -> (u8,u8,u8)) -> String {
+        let mut out = format!("P3\n{} {}\n255\n", self.width, self.height);
+        for row in self.rows() {
+            let values: Vec<String> = row.iter().map(|v| {
+                let (r,g,b) = color(*v);
+                format!("{r} {g} {b}")
+            }).collect();
+            out.push_str(&values.join("  "));
+            out.push('\n');
         }
+        out
+    }
+}
+
+// Scales a score between 0 and max to 0..=255
+fn level(score: u64, max: u64) -> u8 {
+    if max == 0 {
+        0
+    } else {
+        (score as u128 * 255 / max as u128) as u8
     }
-    visible.len()
 }
 
-fn run2(input: &str) -> u32 {
-    let mut heights = Vec::new();
-    // Read input
-    for line in input.lines() {
-        let mut row = Vec::new();
-        for c in line.chars() {
-            row.push(c.to_digit(10).unwrap());
+// Black for the lowest scores, then red, yellow and white for the highest
+fn heat(score: u64, max: u64) -> (u8,u8,u8) {
+    let l = level(score, max) as u32 * 3;
+    let channel = |from: u32| (l.saturating_sub(from)).min(255) as u8;
+    (channel(0), channel(255), channel(510))
+}
+
+// Position of the tree with the highest scenic score, the first one in reading order on ties
+fn best_tree(scenic: &Grid<u64>) -> Option<(usize,usize,u64)> {
+    let mut best: Option<(usize,usize,u64)> = None;
+    for (k,s) in scenic.cells.iter().enumerate() {
+        if best.is_none_or(|(_,_,b)| *s > b) {
+            best = Some((k / scenic.width, k % scenic.width, *s));
         }
-        heights.push(row);
-    }
-    let mut max = 0;
-    for i in 0..heights.len() {
-        for j in 0..heights[i].len() {
-            let h = heights[i][j];
-            let mut score = 1;
-            // Count strictly lower trees to the left
-            let mut sh = 0;
-            let mut nj = j;
-            while nj > 0 {
-                nj -= 1;
-                sh += 1;
-                if heights[i][nj] >= h {
-                    break;
-                }
-            }
-            if sh > 0 {
-                score *= sh;
-            }
-            // Count strictly lower trees to the right
-            sh = 0;
-            nj = j;
-            while nj < heights[i].len() - 1 {
-                nj += 1;
-                sh += 1;
-                if heights[i][nj] >= h {
-                    break;
-                }
-            }
-            if sh > 0 {
-                score *= sh;
-            }
+    }
+    best
+}
 
-            // Count strictly lower trees upward
-            let mut sv = 0;
-            let mut ni = i;
-            while ni > 0 {
-                ni -= 1;
-                sv += 1;
-                if heights[ni][j] >= h {
-                    break;
-                }
-            }
-            if sv > 0 {
-                score *= sv;
-            }
-            // Count strictly lower trees downward
-            sv = 0;
-            ni = i;
-            while ni < heights.len() - 1 {
-                ni += 1;
-                sv += 1;
-                if heights[ni][j] >= h {
-                    break;
-                }
-            }
-            if sv > 0 {
-                score *= sv;
-            }
+// Writes the visibility mask and the scenic scores to the given directory, as images and as CSV
+fn export(heights: &Grid<u8>, dir: &Path) -> io::Result<()> {
+    let (visible, scenic) = views(heights);
+    let max = scenic.cells.iter().copied().max().unwrap_or(0);
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("visible.pgm"), visible.to_pgm(|v| if v { 255 } else { 0 }))?;
+    fs::write(dir.join("visible.csv"), visible.map(|v| v as u8).to_csv())?;
+    fs::write(dir.join("scenic.pgm"), scenic.to_pgm(|s| level(s, max)))?;
+    fs::write(dir.join("scenic.ppm"), scenic.to_ppm(|s| heat(s, max)))?;
+    fs::write(dir.join("scenic.csv"), scenic.to_csv())?;
+    Ok(())
+}
 
-            println!("({i},{j}): {score}");
-            if score > max {
-                max = score;
-            }
+impl Grid<u8> {
+    fn parse(input: &str) -> Self {
+        let rows: Vec<Vec<u8>> = input.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| l.trim().chars().map(|c| c.to_digit(10).unwrap() as u8).collect())
+            .collect();
+        let width = rows.first().map_or(0, |r| r.len());
+        assert!(rows.iter().all(|r| r.len() == width), "All rows of the forest must have the same length");
+        Self { width, height: rows.len(), cells: rows.concat() }
+    }
+}
+
+// Walks a line of trees in order, keeping a stack of the trees not hidden yet by a taller or equal
+// one. The top of the stack after popping the lower trees is the first one blocking the view
+fn scan(heights: &Grid<u8>, line: impl Iterator<Item = (usize,usize)>, visible: &mut Grid<bool>, scenic: &mut Grid<u64>, skip_edges: bool) {
+    let mut stack: Vec<(usize,u8)> = Vec::new();
+    for (k,(i,j)) in line.enumerate() {
+        let h = heights.get(i, j);
+        while stack.last().is_some_and(|(_,sh)| *sh < h) {
+            stack.pop();
+        }
+        let distance = match stack.last() {
+            Some((sk,_)) => k - sk,
+            None => {
+                visible.set(i, j, true);
+                k
+            },
+        };
+        if distance > 0 || !skip_edges {
+            scenic.set(i, j, scenic.get(i, j) * distance as u64);
         }
+        stack.push((k,h));
     }
-    max
+}
+
+// Whether each tree is visible from outside the forest, and its scenic score: the product of the
+// viewing distances in the four directions. Linear in the number of trees
+fn views(heights: &Grid<u8>) -> (Grid<bool>, Grid<u64>) {
+    views_with(heights, false)
+}
+
+// With skip_edges, viewing distances of 0 are left out of the product instead of zeroing it, as
+// the original solution for part 2 did
+fn views_with(heights: &Grid<u8>, skip_edges: bool) -> (Grid<bool>, Grid<u64>) {
+    let (w, h) = (heights.width, heights.height);
+    let mut visible = Grid::new(w, h, false);
+    let mut scenic = Grid::new(w, h, 1);
+    for i in 0..h {
+        scan(heights, (0..w).map(|j| (i,j)), &mut visible, &mut scenic, skip_edges);
+        scan(heights, (0..w).rev().map(|j| (i,j)), &mut visible, &mut scenic, skip_edges);
+    }
+    for j in 0..w {
+        scan(heights, (0..h).map(|i| (i,j)), &mut visible, &mut scenic, skip_edges);
+        scan(heights, (0..h).rev().map(|i| (i,j)), &mut visible, &mut scenic, skip_edges);
+    }
+    (visible, scenic)
+}
+
+fn run1(input: &str) -> usize {
+    let (visible, _) = views(&Grid::parse(input));
+    visible.cells.iter().filter(|v| **v).count()
+}
+
+// Keeps the answer of the original solution, where trees on the edge still get a score
+fn run2(input: &str) -> u64 {
+    let (_, scenic) = views_with(&Grid::parse(input), true);
+    scenic.cells.iter().copied().max().unwrap_or(0)
 }
 
 fn main() {
@@ -160,8 +194,37 @@ fn main() {
 
     let input = fs::read_to_string(filepath).unwrap();
 
-    let res = run2(&input);
-    println!("{res}");
+    match args.next().as_deref() {
+        Some("visible") => println!("{}", run1(&input)),
+        Some("best") => {
+            let (_, scenic) = views(&Grid::parse(&input));
+            match best_tree(&scenic) {
+                Some((i,j,score)) => println!("({i},{j}): {score}"),
+                None => println!("There are no trees"),
+            }
+        },
+        Some("export") => {
+            let dir = args.next().unwrap_or(String::from("."));
+            if let Err(e) = export(&Grid::parse(&input), Path::new(&dir)) {
+                eprintln!("Cannot export to {dir}: {e}");
+                process::exit(1);
+            }
+        },
+        Some("maps") => {
+            let (visible, scenic) = views(&Grid::parse(&input));
+            for row in visible.rows() {
+                println!("{}", row.iter().map(|v| if *v { '#' } else { '.' }).collect::<String>());
+            }
+            println!();
+            for row in scenic.rows() {
+                println!("{}", row.iter().map(|s| s.to_string()).collect::<Vec<String>>().join(" "));
+            }
+        },
+        _ => {
+            let res = run2(&input);
+            println!("{res}");
+        },
+    }
 }
 
 #[test]
@@ -191,3 +254,57 @@ fn input2() {
     let res = run2(&input);
     assert_eq!(res,263670);
 }
+
+#[cfg(test)]
+const EXAMPLE: &str = "30373
+25512
+65332
+33549
+35390
+";
+
+#[test]
+fn maps() {
+    let heights = Grid::parse(EXAMPLE);
+    let (visible, scenic) = views(&heights);
+    let mask: Vec<String> = visible.rows().map(|r| r.iter().map(|v| if *v { '#' } else { '.' }).collect()).collect();
+    assert_eq!(mask, vec!["#####","###.#","##.##","#.#.#","#####"]);
+    assert_eq!(scenic.get(1, 2), 4);
+    assert_eq!(scenic.get(3, 2), 8);
+    assert_eq!(scenic.get(0, 3), 0);
+    assert_eq!(scenic.cells.iter().max(), Some(&8));
+    assert_eq!(views_with(&heights, true).1.get(0, 3), 12);
+    assert_eq!(run1(EXAMPLE), 21);
+    assert_eq!(run2(EXAMPLE), 16);
+}
+
+#[test]
+fn images() {
+    let (visible, scenic) = views(&Grid::parse(EXAMPLE));
+    assert_eq!(best_tree(&scenic), Some((3,2,8)));
+    assert_eq!(visible.map(|v| v as u8).to_csv().lines().nth(2), Some("1,1,0,1,1"));
+    assert_eq!(scenic.to_csv().lines().nth(1), Some("0,1,4,1,0"));
+    let pgm = visible.to_pgm(|v| if v { 255 } else { 0 });
+    assert!(pgm.starts_with("P2\n5 5\n255\n255 255 255 255 255\n255 255 255 0 255\n"));
+    let ppm = scenic.to_ppm(|s| heat(s, 8));
+    assert_eq!(ppm.lines().count(), 8);
+    assert_eq!(heat(16, 16), (255,255,255));
+    assert_eq!(heat(0, 16), (0,0,0));
+    let (visible, scenic) = views(&Grid::parse(""));
+    assert_eq!(visible.rows().count() + scenic.rows().count(), 0);
+    assert_eq!(scenic.to_pgm(|s| level(s, 0)), "P2\n0 0\n255\n");
+    assert_eq!(best_tree(&scenic), None);
+}
+
+#[test]
+fn exported_best() {
+    let dir = std::env::temp_dir().join("day8_exported_best");
+    export(&Grid::parse(EXAMPLE), &dir).unwrap();
+    let csv = fs::read_to_string(dir.join("scenic.csv")).unwrap();
+    let scores: Vec<Vec<u64>> = csv.lines().map(|l| l.split(',').map(|s| s.parse().unwrap()).collect()).collect();
+    // The best tree of the puzzle, with no tree on the edge scoring anything
+    assert_eq!(scores[3][2], 8);
+    assert!(scores.iter().flatten().all(|s| *s <= 8));
+    assert!(scores[0].iter().chain(&scores[4]).all(|s| *s == 0));
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -1,8 +1,8 @@
 use std::{env,fs,process};
-use std::collections::HashSet;
+use std::collections::{HashMap,HashSet};
 
-#[derive(Clone,Copy,Debug)]
-enum Dir { U, D, L, R, }
+#[derive(Clone,Copy,Debug,PartialEq)]
+enum Dir { U, D, L, R, UR, UL, DR, DL, }
 
 fn plus(pos: (i32,i32), n: i32, dir: &Dir) -> (i32,i32) {
     match dir {
@@ -10,6 +10,10 @@ fn plus(pos: (i32,i32), n: i32, dir: &Dir) -> (i32,i32) {
         Dir::D => (pos.0, pos.1 - n),
         Dir::R => (pos.0 + n, pos.1),
         Dir::L => (pos.0 - n, pos.1),
+        Dir::UR => (pos.0 + n, pos.1 + n),
+        Dir::UL => (pos.0 - n, pos.1 + n),
+        Dir::DR => (pos.0 + n, pos.1 - n),
+        Dir::DL => (pos.0 - n, pos.1 - n),
     }
 }
 
@@ -23,102 +27,353 @@ fn distance(x: (i32,i32), y: (i32,i32)) -> i32 {
     }
 }
 
-fn run1(input: &str) -> usize {
-    let mut head = (0,0);
-    let mut tail = (0,0);
-    let mut visited = HashSet::new();
-    visited.insert(tail);
-    for line in input.lines() {
-        // Parse input
-        let words = line.split(' ').collect::<Vec<&str>>();
-        let dir = match words[0] {
-            "R" => Dir::R,
-            "L" => Dir::L,
-            "U" => Dir::U,
-            "D" => Dir::D,
-            s => panic!("Unexpected input {s}"),
-        };
-        let n = words[1].parse::<i32>().unwrap();
-        for _ in 0..n {
-            // Move the head
-            head = plus(head, 1, &dir);
-            // If the supremum distance is 1 or less, do nothing
-            if distance(head, tail) > 1 {
-                // Move tail to the right place with respect to head
-                tail = snap(head,tail);
-                //tail = match dir {
-                    //Dir::R => (head.0 - 1, head.1),
-                    //Dir::L => (head.0 + 1, head.1),
-                    //Dir::U => (head.0, head.1 - 1),
-                    //Dir::D => (head.0, head.1 + 1),
-                //};
-                visited.insert(tail);
-            }
-        }
-    }
-    visited.len()
+// How a knot follows the one ahead of it
+#[derive(Clone,Copy,Debug,PartialEq)]
+enum Follow {
+    // Steps (diagonally if needed) until it touches the previous knot, diagonals included
+    King,
+    // Steps only horizontally or vertically, until it shares a side with the previous knot
+    Manhattan,
+    // Like King, but it lets the previous knot get up to this many cells away before moving
+    Elastic(i32),
 }
 
-// "Snap" pos1 to pos0, and return the desired new position for pos1
-// Assume that distance(pos0,pos1) > 1
-// Moreover, in one of the coordinates we have that |x.i - y.i| <= 1
-// We must place pos1 right behind pos0 in the other coordinate
+// Moves pos1 one step towards pos0, diagonally if they are in different rows and columns
 fn snap(pos0: (i32,i32), pos1: (i32,i32)) -> (i32,i32) {
-    if (pos0.0 - pos1.0).abs() <= 1 {
-        // We move vertically, and just take pos0.0
-        if pos0.1 < pos1.1 {
-            // pos0 is below, follow it from above
-            (pos0.0, pos0.1 + 1)
-        } else {
-            // pos0 is above, follow it from below
-            (pos0.0, pos0.1 - 1)
-        }
-    } else if (pos0.1 - pos1.1).abs() <= 1 {
-        // We move horizontally, and just take pos0.1
-        if pos0.0 < pos1.0 {
-            // pos0 is to the left, follow it from the right
-            (pos0.0 + 1, pos0.1)
-        } else {
-            // pos0 is to the right, follow it from the left
-            (pos0.0 - 1, pos0.1)
-        }        
-    } else if distance(pos0,pos1) == 2 {
-        ((pos0.0 + pos1.0) / 2, (pos0.1 + pos1.1) / 2)
+    (pos1.0 + (pos0.0 - pos1.0).signum(), pos1.1 + (pos0.1 - pos1.1).signum())
+}
+
+// Moves pos1 one step towards pos0, along the coordinate where they are further apart
+fn snap_orthogonal(pos0: (i32,i32), pos1: (i32,i32)) -> (i32,i32) {
+    if (pos0.0 - pos1.0).abs() >= (pos0.1 - pos1.1).abs() {
+        (pos1.0 + (pos0.0 - pos1.0).signum(), pos1.1)
     } else {
-        panic!("Unexpected error: pos0: {pos0:?}; pos1: {pos1:?}");
+        (pos1.0, pos1.1 + (pos0.1 - pos1.1).signum())
     }
 }
 
-fn run2(input: &str) -> usize {
-    let mut snake = vec![(0,0);10];
-    let mut visited = HashSet::new();
-    visited.insert((0,0));
-    for line in input.lines() {
-        // Parse input
-        let words = line.split(' ').collect::<Vec<&str>>();
-        let dir = match words[0] {
-            "R" => Dir::R,
-            "L" => Dir::L,
-            "U" => Dir::U,
-            "D" => Dir::D,
-            s => panic!("Unexpected input {s}"),
-        };
-        let n = words[1].parse::<i32>().unwrap();
+struct Rope {
+    // Head first
+    knots: Vec<(i32,i32)>,
+    rule: Follow,
+    // How many times each knot has entered each position. The keys are the positions it has been in
+    visits: Vec<HashMap<(i32,i32),u32>>,
+    // Positions of all the knots after every move of the head, when recording
+    frames: Option<Vec<Vec<(i32,i32)>>>,
+}
+
+impl Rope {
+    fn new(length: usize, rule: Follow) -> Self {
+        assert!(length > 0, "A rope needs at least one knot");
+        let visits = HashMap::from([((0,0),1)]);
+        Self {
+            knots: vec![(0,0); length],
+            rule,
+            visits: vec![visits; length],
+            frames: None,
+        }
+    }
+
+    // Keep the position of every knot after each move of the head
+    fn recording(mut self) -> Self {
+        self.frames = Some(vec![self.knots.clone()]);
+        self
+    }
+
+    fn visit(&mut self, i: usize, pos: (i32,i32)) {
+        self.knots[i] = pos;
+        *self.visits[i].entry(pos).or_insert(0) += 1;
+    }
+
+    // Moves knot i after the previous one has moved. A knot left far behind keeps stepping
+    // until it catches up, so it visits every position along the way
+    fn follow(&mut self, i: usize) {
+        loop {
+            let (prev, knot) = (self.knots[i-1], self.knots[i]);
+            let next = match self.rule {
+                Follow::King if distance(prev, knot) > 1 => snap(prev, knot),
+                Follow::Elastic(slack) if distance(prev, knot) > slack.max(1) => snap(prev, knot),
+                Follow::Manhattan if (prev.0 - knot.0).abs() + (prev.1 - knot.1).abs() > 1 => snap_orthogonal(prev, knot),
+                _ => break,
+            };
+            self.visit(i, next);
+        }
+    }
+
+    // Puts the head anywhere, and lets the rest of the knots catch up
+    fn move_head_to(&mut self, pos: (i32,i32)) {
+        self.visit(0, pos);
+        for i in 1..self.knots.len() {
+            self.follow(i);
+        }
+        if let Some(frames) = self.frames.as_mut() {
+            frames.push(self.knots.clone());
+        }
+    }
+
+    // Moves the head n cells in the given direction, one at a time
+    fn step(&mut self, n: i32, dir: &Dir) {
         for _ in 0..n {
-            // Move the head
-            snake[0] = plus(snake[0], 1, &dir);
-            // Move the rest of the snake
-            for i in 1..snake.len() {
-                // If the supremum distance is 1 or less, do nothing
-                if distance(snake[i-1], snake[i]) > 1 {
-                    // Move tail to the right place with respect to the knot
-                    snake[i] = snap(snake[i-1], snake[i]);
+            self.move_head_to(plus(self.knots[0], 1, dir));
+        }
+    }
+
+    // Every position knot i has been in
+    fn visited(&self, i: usize) -> HashSet<(i32,i32)> {
+        self.visits[i].keys().copied().collect()
+    }
+
+    fn visited_count(&self, i: usize) -> usize {
+        self.visits[i].len()
+    }
+
+    fn tail(&self) -> usize {
+        self.knots.len() - 1
+    }
+
+    fn tail_visited(&self) -> HashSet<(i32,i32)> {
+        self.visited(self.tail())
+    }
+}
+
+#[derive(Clone,Debug,PartialEq)]
+enum Command {
+    // Move the head n cells in a direction, one at a time
+    Step(Dir, i32),
+    // Put the head at the given position straight away
+    Jump((i32,i32)),
+    // Run a block of commands several times
+    Repeat(usize, Vec<Command>),
+}
+
+// One command per line:
+//   R 4, UR 3, DL 2...  steps in one of the eight directions
+//   J 10 -3             jump to an absolute position
+//   REPEAT 3 ... END    repeat a block, which may contain other blocks
+fn parse_commands(input: &str) -> Result<Vec<Command>, String> {
+    // Blocks being read, innermost last, with their repetitions
+    let mut blocks: Vec<(usize, Vec<Command>)> = vec![(1, vec![])];
+    for (k,line) in input.lines().enumerate() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |s: &str| s.parse::<i32>().map_err(|_| format!("Line {}: invalid number {s}", k + 1));
+        let command = match words[..] {
+            [] => continue,
+            ["REPEAT", n] => {
+                let n = n.parse::<usize>().map_err(|_| format!("Line {}: invalid repetitions {n}", k + 1))?;
+                blocks.push((n, vec![]));
+                continue;
+            },
+            ["END"] => {
+                if blocks.len() == 1 {
+                    return Err(format!("Line {}: END without REPEAT", k + 1));
+                }
+                let (n, block) = blocks.pop().unwrap();
+                Command::Repeat(n, block)
+            },
+            ["J", x, y] => Command::Jump((number(x)?, number(y)?)),
+            [d, n] => {
+                let dir = match d {
+                    "R" => Dir::R,
+                    "L" => Dir::L,
+                    "U" => Dir::U,
+                    "D" => Dir::D,
+                    "UR" | "RU" => Dir::UR,
+                    "UL" | "LU" => Dir::UL,
+                    "DR" | "RD" => Dir::DR,
+                    "DL" | "LD" => Dir::DL,
+                    s => return Err(format!("Line {}: unexpected direction {s}", k + 1)),
+                };
+                let n = number(n)?;
+                if n < 0 {
+                    return Err(format!("Line {}: cannot take {n} steps", k + 1));
                 }
+                Command::Step(dir, n)
+            },
+            _ => return Err(format!("Line {}: unexpected command {line}", k + 1)),
+        };
+        blocks.last_mut().unwrap().1.push(command);
+    }
+    if blocks.len() > 1 {
+        return Err(String::from("Missing END at the end of the input"));
+    }
+    Ok(blocks.pop().unwrap().1)
+}
+
+impl Rope {
+    fn run(&mut self, commands: &[Command]) {
+        for c in commands {
+            match c {
+                Command::Step(dir, n) => self.step(*n, dir),
+                Command::Jump(pos) => self.move_head_to(*pos),
+                Command::Repeat(n, block) => {
+                    for _ in 0..*n {
+                        self.run(block);
+                    }
+                },
             }
-            visited.insert(*snake.last().unwrap());
         }
     }
-    visited.len()
+}
+
+fn simulate_rope(input: &str, mut rope: Rope) -> Result<Rope, String> {
+    let commands = parse_commands(input)?;
+    rope.run(&commands);
+    Ok(rope)
+}
+
+fn simulate(input: &str, length: usize, rule: Follow) -> Result<Rope, String> {
+    simulate_rope(input, Rope::new(length, rule))
+}
+
+// Smallest and largest coordinates of some cells
+fn bounds(cells: impl Iterator<Item = (i32,i32)>) -> Option<((i32,i32),(i32,i32))> {
+    cells.fold(None, |b, (x,y)| match b {
+        None => Some(((x,y),(x,y))),
+        Some(((x0,y0),(x1,y1))) => Some(((x0.min(x),y0.min(y)),(x1.max(x),y1.max(y)))),
+    })
+}
+
+// Draws the cells inside the bounds with y growing upwards, as in the puzzle statement
+fn draw(((x0,y0),(x1,y1)): ((i32,i32),(i32,i32)), cell: impl Fn((i32,i32)) -> char) -> String {
+    let mut out = String::new();
+    for y in (y0..=y1).rev() {
+        out.extend((x0..=x1).map(|x| cell((x,y))));
+        out.push('\n');
+    }
+    out
+}
+
+// Positions visited by a knot marked with #, and the start with s
+fn render_visited(visited: &HashSet<(i32,i32)>) -> String {
+    let b = bounds(visited.iter().copied().chain([(0,0)])).unwrap();
+    draw(b, |p| if p == (0,0) {
+        's'
+    } else if visited.contains(&p) {
+        '#'
+    } else {
+        '.'
+    })
+}
+
+// Knots labelled H, then 1, 2... (or T for the tail of a two-knot rope). Knots closer to the head
+// hide the ones behind them
+fn render_knots(knots: &[(i32,i32)], b: ((i32,i32),(i32,i32))) -> String {
+    let label = |i: usize| match i {
+        0 => 'H',
+        1 if knots.len() == 2 => 'T',
+        i => char::from_digit(i as u32 % 36, 36).unwrap(),
+    };
+    draw(b, |p| match knots.iter().position(|k| *k == p) {
+        Some(i) => label(i),
+        None if p == (0,0) => 's',
+        None => '.',
+    })
+}
+
+// Every recorded state of the rope, all drawn over the same area
+fn replay(rope: &Rope) -> String {
+    let frames = rope.frames.as_deref().unwrap_or_default();
+    let Some(b) = bounds(frames.iter().flatten().copied().chain([(0,0)])) else {
+        return String::new();
+    };
+    let pictures: Vec<String> = frames.iter().enumerate()
+        .map(|(k,f)| format!("== Step {k} ==\n\n{}", render_knots(f, b)))
+        .collect();
+    pictures.join("\n")
+}
+
+// Darker to brighter characters for fewer to more visits
+fn render_heatmap(visits: &HashMap<(i32,i32),u32>) -> String {
+    const RAMP: &[u8] = b" .:-=+*#%@";
+    let max = visits.values().copied().max().unwrap_or(0);
+    let b = bounds(visits.keys().copied()).unwrap_or(((0,0),(0,0)));
+    draw(b, |p| match visits.get(&p) {
+        Some(n) => RAMP[(*n * (RAMP.len() as u32 - 1)).div_ceil(max) as usize] as char,
+        None => RAMP[0] as char,
+    })
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in bytes {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for x in bytes {
+        a = (a + *x as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+// Grayscale PNG image, stored without compression
+fn png(width: usize, height: usize, gray: &[u8]) -> Vec<u8> {
+    fn chunk(out: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+        out.extend((data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend(kind);
+        out.extend(data);
+        let crc = crc32(&out[start..]);
+        out.extend(crc.to_be_bytes());
+    }
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut header = Vec::new();
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // 8 bits per pixel, grayscale, default compression, filter and no interlacing
+    header.extend([8, 0, 0, 0, 0]);
+    chunk(&mut out, b"IHDR", &header);
+    // Every row starts with its filter type, none
+    let mut raw = Vec::with_capacity((width + 1) * height);
+    for row in gray.chunks(width.max(1)) {
+        raw.push(0);
+        raw.extend(row);
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = raw.chunks(65535).collect();
+    for (k,block) in blocks.iter().enumerate() {
+        zlib.push((k + 1 == blocks.len()) as u8);
+        let len = block.len() as u16;
+        zlib.extend(len.to_le_bytes());
+        zlib.extend((!len).to_le_bytes());
+        zlib.extend(*block);
+    }
+    zlib.extend(adler32(&raw).to_be_bytes());
+    chunk(&mut out, b"IDAT", &zlib);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+// Brightness of each position proportional to the number of visits
+fn heatmap_png(visits: &HashMap<(i32,i32),u32>) -> Vec<u8> {
+    let ((x0,y0),(x1,y1)) = bounds(visits.keys().copied()).unwrap_or(((0,0),(0,0)));
+    let max = visits.values().copied().max().unwrap_or(1) as u64;
+    let (width, height) = ((x1 - x0 + 1) as usize, (y1 - y0 + 1) as usize);
+    let mut gray = Vec::with_capacity(width * height);
+    for y in (y0..=y1).rev() {
+        for x in x0..=x1 {
+            let n = visits.get(&(x,y)).copied().unwrap_or(0) as u64;
+            gray.push((n * 255 / max) as u8);
+        }
+    }
+    png(width, height, &gray)
+}
+
+fn run1(input: &str) -> usize {
+    let rope = simulate(input, 2, Follow::King).unwrap_or_else(|e| panic!("{e}"));
+    rope.visited_count(rope.tail())
+}
+
+fn run2(input: &str) -> usize {
+    let rope = simulate(input, 10, Follow::King).unwrap_or_else(|e| panic!("{e}"));
+    rope.visited_count(rope.tail())
 }
 
 fn main() {
@@ -135,8 +390,77 @@ fn main() {
 
     let input = fs::read_to_string(filepath).unwrap();
 
-    let res = run2(&input);
-    println!("{res}");
+    let mode = args.next();
+    let length = match mode.as_deref() {
+        None => {
+            let res = run2(&input);
+            println!("{res}");
+            return;
+        },
+        Some("short") => {
+            let res = run1(&input);
+            println!("{res}");
+            return;
+        },
+        Some("map" | "heatmap" | "replay" | "png") => args.next(),
+        Some(_) => mode.clone(),
+    };
+    let length = match length.as_deref().map(|s| s.parse::<usize>()) {
+        None => 10,
+        Some(Ok(length)) if length > 0 => length,
+        _ => {
+            eprintln!("A rope needs a positive number of knots");
+            process::exit(1);
+        },
+    };
+    // Follow rule: king, manhattan or a number of cells of elastic slack
+    let rule = match args.next().as_deref() {
+        None | Some("king") => Follow::King,
+        Some("manhattan") => Follow::Manhattan,
+        Some(s) => match s.parse::<i32>() {
+            Ok(slack) => Follow::Elastic(slack),
+            Err(_) => {
+                eprintln!("Unknown follow rule {s}");
+                process::exit(1);
+            },
+        },
+    };
+    let mut rope = Rope::new(length, rule);
+    if mode.as_deref() == Some("replay") {
+        rope = rope.recording();
+    }
+    let rope = simulate_rope(&input, rope).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(1);
+    });
+    match mode.as_deref() {
+        Some("map") => print!("{}", render_visited(&rope.tail_visited())),
+        Some("replay") => print!("{}", replay(&rope)),
+        Some("heatmap") => {
+            for (i,v) in rope.visits.iter().enumerate() {
+                let ((x0,y0),(x1,y1)) = bounds(v.keys().copied()).unwrap();
+                println!("== Knot {i}: {} positions, from ({x0},{y0}) to ({x1},{y1}) ==", v.len());
+                println!();
+                print!("{}", render_heatmap(v));
+                println!();
+            }
+        },
+        Some("png") => {
+            for (i,v) in rope.visits.iter().enumerate() {
+                let name = format!("knot{i}.png");
+                if let Err(e) = fs::write(&name, heatmap_png(v)) {
+                    eprintln!("Cannot write {name}: {e}");
+                    process::exit(1);
+                }
+            }
+        },
+        _ => {
+            for i in 0..rope.knots.len() {
+                println!("Knot {i}: {}", rope.visited_count(i));
+            }
+            println!("Tail: {}", rope.visited_count(rope.tail()));
+        },
+    }
 }
 
 #[test]
@@ -173,3 +497,108 @@ fn input2() {
     let res = run2(&input);
     assert_eq!(res,2545);
 }
+
+#[cfg(test)]
+const EXAMPLE: &str = "R 4
+U 4
+L 3
+D 1
+R 4
+D 1
+L 5
+R 2
+";
+
+#[cfg(test)]
+const EXAMPLE2: &str = "R 5
+U 8
+L 8
+D 3
+R 17
+D 10
+L 25
+U 20
+";
+
+#[test]
+fn ropes() {
+    assert_eq!(run1(EXAMPLE), 13);
+    assert_eq!(run2(EXAMPLE), 1);
+    assert_eq!(run2(EXAMPLE2), 36);
+    let rope = simulate(EXAMPLE, 10, Follow::King).unwrap();
+    assert_eq!(rope.visited_count(1), 13);
+    assert_eq!(rope.visited_count(0), (0..10).map(|i| rope.visited_count(i)).max().unwrap());
+    assert_eq!(rope.visited(1).len(), 13);
+    // A slack of 1 is the usual rope
+    let elastic = simulate(EXAMPLE2, 10, Follow::Elastic(1)).unwrap();
+    assert_eq!(elastic.tail_visited(), simulate(EXAMPLE2, 10, Follow::King).unwrap().tail_visited());
+}
+
+#[test]
+fn far_moves() {
+    let mut rope = Rope::new(3, Follow::King);
+    rope.move_head_to((5,2));
+    assert_eq!(rope.knots, vec![(5,2),(4,2),(3,2)]);
+    assert_eq!(rope.visited_count(1), 5);
+    let mut rope = Rope::new(2, Follow::Manhattan);
+    rope.move_head_to((2,2));
+    assert_eq!(rope.knots[1], (2,1));
+    assert_eq!(rope.visited_count(1), 4);
+    let mut rope = Rope::new(2, Follow::Elastic(3));
+    rope.step(3, &Dir::R);
+    rope.step(3, &Dir::U);
+    assert_eq!(rope.knots[1], (0,0));
+    rope.step(1, &Dir::R);
+    assert_eq!(rope.knots[1], (1,1));
+}
+
+#[test]
+fn commands() {
+    let program = "REPEAT 2
+UR 2
+REPEAT 3
+L 1
+END
+END
+J 0 -4
+";
+    let commands = parse_commands(program).unwrap();
+    assert_eq!(commands, vec![
+        Command::Repeat(2, vec![Command::Step(Dir::UR, 2), Command::Repeat(3, vec![Command::Step(Dir::L, 1)])]),
+        Command::Jump((0,-4)),
+    ]);
+    let rope = simulate(program, 2, Follow::King).unwrap();
+    assert_eq!(rope.knots, vec![(0,-4),(0,-3)]);
+    assert!(rope.tail_visited().contains(&(1,1)));
+    assert!(rope.tail_visited().contains(&(0,2)));
+    assert_eq!(parse_commands("X 2"), Err(String::from("Line 1: unexpected direction X")));
+    assert!(parse_commands("REPEAT 2\nR 1\n").is_err());
+    assert!(parse_commands("END\n").is_err());
+}
+
+#[test]
+fn rendering() {
+    let rope = simulate(EXAMPLE2, 10, Follow::King).unwrap();
+    let map = render_visited(&rope.tail_visited());
+    assert_eq!(map.lines().next(), Some("#....................."));
+    assert_eq!(map.lines().nth(6), Some("....#......s.........#"));
+    assert_eq!(map.matches('#').count() + 1, 36);
+    let rope = simulate_rope("R 4\nU 4\n", Rope::new(10, Follow::King).recording()).unwrap();
+    let frames = rope.frames.as_ref().unwrap();
+    assert_eq!(frames.len(), 9);
+    assert_eq!(render_knots(&frames[8], ((0,0),(5,5))), "......\n....H.\n....1.\n..432.\n.5....\n6.....\n");
+    assert_eq!(replay(&rope).matches("== Step").count(), 9);
+    let b = bounds(rope.visits[2].keys().copied());
+    assert_eq!(b, Some(((0,0),(4,2))));
+    assert_eq!(render_heatmap(&rope.visits[9]), "@\n");
+    assert_eq!(render_heatmap(&HashMap::from([((0,0),1),((1,0),3),((3,0),9)])), ".- @\n");
+}
+
+#[test]
+fn png_image() {
+    let image = png(2, 2, &[0, 255, 255, 0]);
+    assert!(image.starts_with(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR"));
+    assert!(image.ends_with(b"IEND\xae\x42\x60\x82"));
+    assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    assert_eq!(crc32(b"IEND"), 0xae426082);
+}